@@ -4,6 +4,8 @@ mod sub_buffer;
 pub use raw_buffer::*;
 pub use sub_buffer::*;
 
+use std::sync::Arc;
+
 use ash::vk;
 
 
@@ -11,6 +13,21 @@ pub trait BufferAllocation {
     fn offset(&self) -> u64 { 0 }
     fn size(&self) -> vk::DeviceSize;
     fn buffer(&self) -> vk::Buffer;
+    fn usage(&self) -> vk::BufferUsageFlags;
+    /// a token that command buffers hold while they use the buffer, `None` if it isn't tracked
+    fn gpu_use(&self) -> Option<GpuUse> { None }
+}
+
+/// held by recorded commands that use a buffer, the buffer counts as in use by the GPU
+/// until every token is dropped, which happens when the submission's fence is seen signaled
+#[derive(Clone, Default)]
+pub struct GpuUse(Arc<()>);
+
+impl GpuUse {
+    /// true while a token handed out by `self` is still alive
+    pub(crate) fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.0) > 1
+    }
 }
 
 
@@ -23,11 +40,15 @@ pub enum BufferSharingMode<'a> {
 
 pub use vk::{BufferUsageFlags, MemoryPropertyFlags};
 
+use crate::prelude::MemoryCategory;
+
 #[derive(Clone, Debug)]
 pub struct BufferCreateInfo<'a> {
     pub usage: vk::BufferUsageFlags,
     pub share_mode: BufferSharingMode<'a>,
     pub visibility: vk::MemoryPropertyFlags,
+    /// usually `MemoryCategory::Buffer`, shows up in `Device::memory_report`
+    pub category: MemoryCategory,
 }
 
 
@@ -1,15 +1,25 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
-use crate::prelude::{BufferCreateInfo, BufferSharingMode, Device};
-use anyhow::{Context, Result};
+use crate::prelude::{
+    Allocation, AllocationKind, AllocationRequest, BufferCreateInfo, BufferSharingMode, Device,
+};
+use anyhow::Result;
 use ash::vk;
 
 #[allow(unused)]
 pub struct RawBuffer {
     handele: vk::Buffer,
     device: Arc<Device>,
+    allocation: Allocation,
+    usage: vk::BufferUsageFlags,
+    size: u64,
     pub requirements: vk::MemoryRequirements,
     pub memory_type_index: u32,
+    // id of the last staged upload into the buffer, 0 if there is none
+    pending_transfer: AtomicU64,
 }
 
 impl RawBuffer {
@@ -27,38 +37,77 @@ impl RawBuffer {
 
         let requirements = unsafe { device.as_raw().get_buffer_memory_requirements(handele) };
 
-        let propeties = device.physical_device_memory_properties();
+        let allocation = device
+            .allocate_memory(&AllocationRequest {
+                requirements,
+                visibility: info.visibility,
+                kind: AllocationKind::Linear,
+                dedicated: false,
+                category: info.category,
+            })
+            .inspect_err(|_| unsafe { device.as_raw().destroy_buffer(handele, None) })?;
 
-        let memory_type_index = find_memorytype_index(&requirements, &propeties, info.visibility)
-            .context("failed to find matching memory_type_index")?;
+        let bind = unsafe {
+            device
+                .as_raw()
+                .bind_buffer_memory(handele, allocation.memory(), allocation.offset())
+        };
+        if let Err(err) = bind {
+            unsafe { device.as_raw().destroy_buffer(handele, None) };
+            device.free_memory(&allocation);
+            return Err(err.into());
+        }
 
         Ok(Self {
             handele,
+            memory_type_index: allocation.memory_type_index(),
             device,
+            allocation,
+            usage: info.usage,
+            size,
             requirements,
-            memory_type_index,
+            pending_transfer: AtomicU64::new(0),
         }
         .into())
     }
+
+    pub fn as_raw(&self) -> &vk::Buffer {
+        &self.handele
+    }
+
+    pub fn allocation(&self) -> &Allocation {
+        &self.allocation
+    }
+
+    pub fn usage(&self) -> vk::BufferUsageFlags {
+        self.usage
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// false while a staged upload into the buffer is still running
+    pub fn is_transfer_finished(&self) -> Result<bool> {
+        match self.pending_transfer.load(Ordering::Relaxed) {
+            0 => Ok(true),
+            id => self.device.is_transfer_finished(id),
+        }
+    }
+
+    /// the buffer waits for the transfer before it's destroyed
+    pub(crate) fn set_pending_transfer(&self, id: u64) {
+        self.pending_transfer.fetch_max(id, Ordering::Relaxed);
+    }
 }
 
 impl Drop for RawBuffer {
     fn drop(&mut self) {
+        let transfer = *self.pending_transfer.get_mut();
+        if transfer != 0 {
+            self.device.wait_transfer(transfer).ok();
+        }
         unsafe { self.device.as_raw().destroy_buffer(self.handele, None) };
+        self.device.free_memory(&self.allocation);
     }
 }
-
-pub fn find_memorytype_index(
-    memory_req: &vk::MemoryRequirements,
-    memory_prop: &vk::PhysicalDeviceMemoryProperties,
-    flags: vk::MemoryPropertyFlags,
-) -> Option<u32> {
-    memory_prop.memory_types[..memory_prop.memory_type_count as _]
-        .iter()
-        .enumerate()
-        .find(|(index, memory_type)| {
-            (1 << index) & memory_req.memory_type_bits != 0
-                && memory_type.property_flags & flags == flags
-        })
-        .map(|(index, _memory_type)| index as _)
-}
@@ -1,66 +1,170 @@
 use ash::vk;
 
-use anyhow::Result;
-use std::{ffi::c_void, sync::Arc};
-
-use crate::prelude::{BufferCreateInfo, Device, RawBuffer};
-
+use anyhow::{bail, Result};
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, Weak},
+};
+
+use crate::prelude::{
+    BufferAllocation, BufferCreateInfo, Device, Fence, GpuUse, RawBuffer, UploadHandle,
+};
+
+/// a typed buffer
+/// host visible buffers stay mapped for as long as they exist, the mapping is owned by the allocator
+/// and goes away when the `RawBuffer` gives its memory back
 #[allow(unused)]
 pub struct Subbuffer<T> {
     buffer: Arc<RawBuffer>,
     size: vk::DeviceSize,
-    memory: vk::DeviceMemory,
     offset: u64,
     device: Arc<Device>,
-    align: Option<(ash::util::Align<T>, *mut c_void)>,
+    // guards borrow this, so there can be many readers or one writer
+    access: RwLock<()>,
+    // handed to command buffers that use the buffer
+    gpu_use: GpuUse,
+    // the last submission that was marked by hand
+    in_use: Mutex<Weak<Fence>>,
+    _marker: PhantomData<T>,
+}
+
+fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr().cast::<u8>(), std::mem::size_of_val(data)) }
 }
 
 impl<T: Copy> Subbuffer<T> {
-    pub fn from_data(device: Arc<Device>, info: BufferCreateInfo, data: &[T]) -> Result<Arc<Self>> {
+    /// buffers without `HOST_VISIBLE` memory are filled through a staging buffer,
+    /// this blocks until the copy finished, use `upload` to not wait for it
+    pub fn from_data(device: Arc<Device>, mut info: BufferCreateInfo, data: &[T]) -> Result<Arc<Self>> {
         let size = std::mem::size_of_val(data) as u64;
 
-        let buffer = RawBuffer::new(device.clone(), info.clone(), size)?;
+        if !info.visibility.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+            info.usage |= vk::BufferUsageFlags::TRANSFER_DST;
+        }
+
+        let buffer = RawBuffer::new(device.clone(), info, size)?;
+
+        let buffer = Self {
+            buffer,
+            size,
+            offset: 0,
+            device,
+            access: RwLock::new(()),
+            gpu_use: GpuUse::default(),
+            in_use: Mutex::new(Weak::new()),
+            _marker: PhantomData,
+        };
+
+        buffer.upload(data)?.wait()?;
+
+        Ok(buffer.into())
+    }
+
+    /// writes `data` to the start of the buffer
+    /// host visible memory is written right away, everything else is copied through a staging buffer
+    /// and needs `BufferUsageFlags::TRANSFER_DST`
+    /// fails if the GPU might still be using the buffer
+    pub fn upload(&self, data: &[T]) -> Result<UploadHandle> {
+        let bytes = as_bytes(data);
+        if bytes.len() as u64 > self.size {
+            bail!(
+                "can't upload {} bytes into a buffer of {} bytes",
+                bytes.len(),
+                self.size
+            );
+        }
 
-        let allocate_info = vk::MemoryAllocateInfo::default()
-            .allocation_size(buffer.requirements.size)
-            .memory_type_index(buffer.memory_type_index);
+        if self.is_host_visible() {
+            self.write()?[..data.len()].copy_from_slice(data);
+            return Ok(UploadHandle::finished(self.device.clone()));
+        }
 
-        let memory = unsafe { device.as_raw().allocate_memory(&allocate_info, None) }?;
+        // the copy runs on the transfer queue, it must not overwrite data the GPU still reads
+        self.check_idle()?;
+        let handle = self.device.upload_to_buffer(self, 0, bytes)?;
+        self.buffer.set_pending_transfer(handle.id());
+        Ok(handle)
+    }
 
-        let align = if info
-            .visibility
-            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
-        {
-            unsafe {
-                let ptr =
-                    device
-                        .as_raw()
-                        .map_memory(memory, 0, size, vk::MemoryMapFlags::empty())?;
+    /// the buffer can't be read or written until `fence` is signaled
+    /// binding the buffer to a command buffer is tracked already, this is for buffers that are
+    /// used through their raw handle
+    pub fn mark_in_use(&self, fence: &Arc<Fence>) {
+        *self.in_use.lock().unwrap() = Arc::downgrade(fence);
+    }
 
-                let mut align = ash::util::Align::new(ptr, std::mem::align_of::<T>() as u64, size);
+    /// fails if the GPU might still be using the buffer
+    fn check_idle(&self) -> Result<()> {
+        if !self.buffer.is_transfer_finished()? {
+            bail!("buffer is still being uploaded");
+        }
 
-                align.copy_from_slice(data);
+        if self.gpu_use.is_shared() {
+            bail!("buffer is still in use by a submission that wasn't seen finished");
+        }
 
-                Some((align, ptr))
+        let fence = self.in_use.lock().unwrap().upgrade();
+        if let Some(fence) = fence {
+            if !fence.is_signaled()? {
+                bail!("buffer is still in use by the GPU");
             }
-        } else {
-            None
+        }
+
+        Ok(())
+    }
+
+    fn mapped_slice(&self) -> Result<*mut T> {
+        let Some(ptr) = self.buffer.allocation().mapped_ptr() else {
+            bail!("buffer isn't host visible");
         };
+        Ok(unsafe { ptr.as_ptr().add(self.offset as usize) }.cast::<T>())
+    }
 
-        Ok(Self {
-            buffer,
-            size,
-            memory,
-            offset: 0,
-            device,
-            align,
-        }
-        .into())
+    /// maps the buffer for reading, non-coherent memory is invalidated first
+    /// fails if the buffer isn't host visible, is in use by the GPU or is being written
+    pub fn read(&self) -> Result<BufferReadGuard<'_, T>> {
+        let ptr = self.mapped_slice()?;
+        let lock = match self.access.try_read() {
+            Ok(lock) => lock,
+            Err(TryLockError::WouldBlock) => bail!("buffer is being written"),
+            Err(TryLockError::Poisoned(err)) => err.into_inner(),
+        };
+        self.check_idle()?;
+
+        self.device
+            .invalidate_memory(self.buffer.allocation(), self.offset, self.size)?;
+
+        Ok(BufferReadGuard {
+            _lock: lock,
+            data: unsafe { std::slice::from_raw_parts(ptr, self.len() as usize) },
+        })
+    }
+
+    /// maps the buffer for writing, non-coherent memory is flushed when the guard is dropped
+    /// fails if the buffer isn't host visible, is in use by the GPU or has other guards
+    pub fn write(&self) -> Result<BufferWriteGuard<'_, T>> {
+        let ptr = self.mapped_slice()?;
+        let lock = match self.access.try_write() {
+            Ok(lock) => lock,
+            Err(TryLockError::WouldBlock) => bail!("buffer is already being read or written"),
+            Err(TryLockError::Poisoned(err)) => err.into_inner(),
+        };
+        self.check_idle()?;
+
+        // parts that aren't overwritten keep what the device wrote
+        self.device
+            .invalidate_memory(self.buffer.allocation(), self.offset, self.size)?;
+
+        Ok(BufferWriteGuard {
+            buffer: self,
+            _lock: lock,
+            data: unsafe { std::slice::from_raw_parts_mut(ptr, self.len() as usize) },
+        })
     }
 
-    pub fn read(&self) -> &[T] {
-        let ptr = self.align.clone().unwrap().1;
-        unsafe { std::slice::from_raw_parts(ptr.cast::<T>(), self.len() as usize) }
+    pub fn is_host_visible(&self) -> bool {
+        self.buffer.allocation().mapped_ptr().is_some()
     }
 
     pub fn size(&self) -> u64 {
@@ -72,8 +176,64 @@ impl<T: Copy> Subbuffer<T> {
     }
 }
 
-impl<T> Drop for Subbuffer<T> {
+pub struct BufferReadGuard<'a, T> {
+    _lock: RwLockReadGuard<'a, ()>,
+    data: &'a [T],
+}
+
+impl<T> Deref for BufferReadGuard<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.data
+    }
+}
+
+pub struct BufferWriteGuard<'a, T> {
+    buffer: &'a Subbuffer<T>,
+    _lock: RwLockWriteGuard<'a, ()>,
+    data: &'a mut [T],
+}
+
+impl<T> Deref for BufferWriteGuard<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.data
+    }
+}
+
+impl<T> DerefMut for BufferWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.data
+    }
+}
+
+impl<T> Drop for BufferWriteGuard<'_, T> {
     fn drop(&mut self) {
-        unsafe { self.device.as_raw().free_memory(self.memory, None) };
+        let buffer = self.buffer;
+        // there is nothing to do about a failed flush here, the device is most likely lost
+        buffer
+            .device
+            .flush_memory(buffer.buffer.allocation(), buffer.offset, buffer.size)
+            .ok();
+    }
+}
+
+impl<T> BufferAllocation for Subbuffer<T> {
+    fn offset(&self) -> u64 {
+        self.offset
+    }
+    fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+    fn buffer(&self) -> vk::Buffer {
+        *self.buffer.as_raw()
+    }
+    fn usage(&self) -> vk::BufferUsageFlags {
+        self.buffer.usage()
+    }
+    fn gpu_use(&self) -> Option<GpuUse> {
+        Some(self.gpu_use.clone())
     }
 }
@@ -1,29 +1,46 @@
 use anyhow::Result;
 use std::sync::Arc;
 
-use crate::prelude::Device;
+use crate::prelude::{Device, Queue};
 use ash::vk;
 
+/// command buffers from a pool can only be submitted to queues of its family
 pub struct CommandPool {
     handle: vk::CommandPool,
     device: Arc<Device>,
+    queue_family_index: u32,
 }
 
 impl CommandPool {
+    /// a pool for the graphics queue
     pub fn new(device: Arc<Device>) -> Result<Arc<Self>> {
+        let family = device.queue_family_index();
+        Self::for_family(device, family)
+    }
+
+    /// a pool for command buffers that get submitted to `queue`
+    pub fn for_queue(device: Arc<Device>, queue: &Queue) -> Result<Arc<Self>> {
+        Self::for_family(device, queue.family_index())
+    }
+
+    pub fn for_family(device: Arc<Device>, queue_family_index: u32) -> Result<Arc<Self>> {
         let pool_create_info = vk::CommandPoolCreateInfo::default()
             .flags(vk::CommandPoolCreateFlags::TRANSIENT)
-            .queue_family_index(device.queue_family_index());
+            .queue_family_index(queue_family_index);
 
         let pool = unsafe { device.as_raw().create_command_pool(&pool_create_info, None) }?;
 
         Ok(Self {
             handle: pool,
             device,
+            queue_family_index,
         }
         .into())
     }
 
+    pub fn queue_family_index(&self) -> u32 {
+        self.queue_family_index
+    }
 
     pub fn as_raw(&self) -> &vk::CommandPool {
         &self.handle
@@ -1,11 +1,16 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 mod command_allocator;
+mod rendering;
 pub use command_allocator::*;
+pub use rendering::*;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 pub use ash::vk; // TODO, make private
 
-use crate::prelude::Device;
+use crate::prelude::{
+    record_mipmaps, record_transition, BufferAllocation, ComputePipeline, DescriptorSets, Device,
+    GpuUse, GraphicsPipeline, Image, Pipeline,
+};
 
 pub use vk::{RenderingInfo, RenderingAttachmentInfo};
 
@@ -13,6 +18,8 @@ pub struct CommandBuffer {
     handle: vk::CommandBuffer,
     device: Arc<Device>,
     allocator: Arc<CommandPool>,
+    // the buffers the recorded commands use, they count as in use until this is dropped
+    gpu_uses: Mutex<Vec<GpuUse>>,
 }
 
 #[allow(unused)]
@@ -39,6 +46,7 @@ impl CommandBuffer {
                 device: device.clone(),
                 handle,
                 allocator: allocator.clone(),
+                gpu_uses: Mutex::default(),
             })
             .collect::<Vec<_>>())
     }
@@ -56,6 +64,9 @@ impl CommandBuffer {
         let begin_info = vk::CommandBufferBeginInfo::default()
             .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
 
+        // beginning resets the commands, so the buffers of earlier recordings aren't used anymore
+        self.gpu_uses.lock().unwrap().clear();
+
         unsafe {
             self.device
                 .as_raw()
@@ -68,16 +79,262 @@ impl CommandBuffer {
         unsafe { self.device.as_raw().cmd_begin_rendering(self.handle, info) }
     }
 
+    pub fn end_rendering(&self) {
+        unsafe { self.device.as_raw().cmd_end_rendering(self.handle) }
+    }
+
+    /// begins dynamic rendering that ends when the returned scope is dropped
+    pub fn rendering(
+        &self,
+        extent: vk::Extent2D,
+        attachments: &RenderingAttachments,
+    ) -> Result<RenderingScope<'_>> {
+        RenderingScope::new(self, extent, attachments)
+    }
+
     pub fn begin_render_pass(&self, info: &vk::RenderPassBeginInfo, contents: vk::SubpassContents ) {
         unsafe { self.device.as_raw().cmd_begin_render_pass(self.handle, info, contents) }
     }
 
+    pub fn end_render_pass(&self) {
+        unsafe { self.device.as_raw().cmd_end_render_pass(self.handle) }
+    }
+
+    pub fn bind_graphics_pipeline(&self, pipeline: &GraphicsPipeline) {
+        unsafe {
+            self.device.as_raw().cmd_bind_pipeline(
+                self.handle,
+                vk::PipelineBindPoint::GRAPHICS,
+                *pipeline.as_raw(),
+            )
+        }
+    }
+
+    /// graphics pipelines always use a dynamic viewport and scissor
+    /// so both need to be set before drawing
+    pub fn set_viewport(&self, viewport: vk::Viewport) {
+        unsafe {
+            self.device
+                .as_raw()
+                .cmd_set_viewport(self.handle, 0, &[viewport])
+        }
+    }
+
+    pub fn set_scissor(&self, scissor: vk::Rect2D) {
+        unsafe {
+            self.device
+                .as_raw()
+                .cmd_set_scissor(self.handle, 0, &[scissor])
+        }
+    }
+
+    pub fn draw(
+        &self,
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.device.as_raw().cmd_draw(
+                self.handle,
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            )
+        }
+    }
+
+    /// binds every buffer from its start, starting at binding `first_binding`
+    /// the buffers count as in use until the command buffer is dropped
+    pub fn bind_vertex_buffers(&self, first_binding: u32, buffers: &[&dyn BufferAllocation]) {
+        self.gpu_uses
+            .lock()
+            .unwrap()
+            .extend(buffers.iter().filter_map(|v| v.gpu_use()));
+
+        let handles: Vec<_> = buffers.iter().map(|v| v.buffer()).collect();
+        let offsets: Vec<_> = buffers.iter().map(|v| v.offset()).collect();
+        unsafe {
+            self.device.as_raw().cmd_bind_vertex_buffers(
+                self.handle,
+                first_binding,
+                &handles,
+                &offsets,
+            )
+        }
+    }
+
+    /// `offset` is relative to the start of `buffer`
+    /// the buffer counts as in use until the command buffer is dropped
+    pub fn bind_index_buffer(
+        &self,
+        buffer: &impl BufferAllocation,
+        offset: u64,
+        index_type: vk::IndexType,
+    ) {
+        self.gpu_uses.lock().unwrap().extend(buffer.gpu_use());
+        unsafe {
+            self.device.as_raw().cmd_bind_index_buffer(
+                self.handle,
+                buffer.buffer(),
+                buffer.offset() + offset,
+                index_type,
+            )
+        }
+    }
+
+    pub fn draw_indexed(
+        &self,
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        vertex_offset: i32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.device.as_raw().cmd_draw_indexed(
+                self.handle,
+                index_count,
+                instance_count,
+                first_index,
+                vertex_offset,
+                first_instance,
+            )
+        }
+    }
+
+    pub fn bind_compute_pipeline(&self, pipeline: &ComputePipeline) {
+        unsafe {
+            self.device.as_raw().cmd_bind_pipeline(
+                self.handle,
+                vk::PipelineBindPoint::COMPUTE,
+                *pipeline.as_raw(),
+            )
+        }
+    }
+
+    /// binds all sets in `sets` starting at set index `first_set`
+    /// the buffers written into the sets count as in use until the command buffer is dropped
+    /// fails if the pool the sets came from was reset
+    pub fn bind_descriptor_sets(
+        &self,
+        pipeline: &impl Pipeline,
+        first_set: u32,
+        sets: &DescriptorSets,
+    ) -> Result<()> {
+        if !sets.is_valid() {
+            bail!("can't bind descriptor sets whose pool was reset");
+        }
+
+        self.gpu_uses.lock().unwrap().extend(sets.gpu_uses());
+        unsafe {
+            self.device.as_raw().cmd_bind_descriptor_sets(
+                self.handle,
+                pipeline.bind_point(),
+                *pipeline.layout(),
+                first_set,
+                sets.as_raw(),
+                &[],
+            )
+        };
+        Ok(())
+    }
+
+    /// `bytes` has to match the layout the shader declares, including its padding
+    pub fn push_constants(
+        &self,
+        pipeline: &impl Pipeline,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        bytes: &[u8],
+    ) {
+        unsafe {
+            self.device.as_raw().cmd_push_constants(
+                self.handle,
+                *pipeline.layout(),
+                stages,
+                offset,
+                bytes,
+            )
+        }
+    }
+
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        unsafe { self.device.as_raw().cmd_dispatch(self.handle, x, y, z) }
+    }
+
+    /// `offset` is relative to the start of `buffer`
+    /// the buffer needs to be created with `BufferUsageFlags::INDIRECT_BUFFER`
+    pub fn dispatch_indirect(&self, buffer: &impl BufferAllocation, offset: u64) {
+        self.gpu_uses.lock().unwrap().extend(buffer.gpu_use());
+        unsafe {
+            self.device.as_raw().cmd_dispatch_indirect(
+                self.handle,
+                buffer.buffer(),
+                buffer.offset() + offset,
+            )
+        }
+    }
+
+    /// moves every level and layer of `image` from its tracked layout to `layout`
+    /// the tracked layout is updated right away, so transitions have to be submitted in the order
+    /// they are recorded
+    pub fn transition_layout(&self, image: &Image, layout: vk::ImageLayout) {
+        let old_layout = image.layout();
+        if old_layout == layout {
+            return;
+        }
+
+        record_transition(
+            self.device.as_raw(),
+            self.handle,
+            *image.as_raw(),
+            image.subresource_range(),
+            old_layout,
+            layout,
+        );
+        image.set_layout(layout);
+    }
+
+    /// fills all mip levels of `image` from level 0, the image ends up in `layout`
+    /// the image needs `TRANSFER_SRC` and `TRANSFER_DST` usage and a format that supports linear blits
+    pub fn generate_mipmaps(&self, image: &Image, layout: vk::ImageLayout) -> Result<()> {
+        if !self.device.supports_linear_blit(image.format()) {
+            anyhow::bail!("{:?} doesn't support linear blits", image.format());
+        }
+
+        self.transition_layout(image, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+        record_mipmaps(
+            self.device.as_raw(),
+            self.handle,
+            *image.as_raw(),
+            image.extent(),
+            image.mip_levels(),
+            image.subresource_range().layer_count,
+            layout,
+        );
+        image.set_layout(layout);
+
+        Ok(())
+    }
+
     /// end recording
     /// needs to be called before submit
     pub fn end(&self) {
         unsafe { self.device.as_raw().end_command_buffer(self.handle) }.unwrap();
     }
 
+    /// the family of the queues the command buffer can be submitted to
+    pub fn queue_family_index(&self) -> u32 {
+        self.allocator.queue_family_index()
+    }
+
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
     pub fn as_raw(&self) -> &vk::CommandBuffer {
         &self.handle
     }
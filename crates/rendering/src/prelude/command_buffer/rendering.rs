@@ -0,0 +1,195 @@
+use std::ops::Deref;
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::{CommandBuffer, ImageView};
+
+/// an image view that is rendered to, with what happens to it before and after
+#[derive(Clone, Copy)]
+pub struct RenderingAttachment {
+    pub view: vk::ImageView,
+    pub layout: vk::ImageLayout,
+    pub load_op: vk::AttachmentLoadOp,
+    pub store_op: vk::AttachmentStoreOp,
+    /// only used with `AttachmentLoadOp::CLEAR`
+    pub clear_value: vk::ClearValue,
+}
+
+impl RenderingAttachment {
+    /// cleared to transparent black and stored, in `COLOR_ATTACHMENT_OPTIMAL`
+    pub fn color(view: vk::ImageView) -> Self {
+        Self {
+            view,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            load_op: vk::AttachmentLoadOp::CLEAR,
+            store_op: vk::AttachmentStoreOp::STORE,
+            clear_value: vk::ClearValue::default(),
+        }
+    }
+
+    /// cleared to 1.0 and not stored, in `DEPTH_STENCIL_ATTACHMENT_OPTIMAL`
+    /// the depth only layouts need `separateDepthStencilLayouts`, which isn't enabled
+    pub fn depth(view: vk::ImageView) -> Self {
+        Self {
+            view,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            load_op: vk::AttachmentLoadOp::CLEAR,
+            store_op: vk::AttachmentStoreOp::DONT_CARE,
+            clear_value: vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        }
+    }
+
+    /// cleared to 0 and not stored, in `DEPTH_STENCIL_ATTACHMENT_OPTIMAL` like `depth`
+    pub fn stencil(view: vk::ImageView) -> Self {
+        Self {
+            clear_value: vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue::default(),
+            },
+            ..Self::depth(view)
+        }
+    }
+
+    pub fn from_view(view: &ImageView) -> Self {
+        Self::color(*view.as_raw())
+    }
+
+    pub fn layout(mut self, layout: vk::ImageLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn load(mut self, load_op: vk::AttachmentLoadOp) -> Self {
+        self.load_op = load_op;
+        self
+    }
+
+    pub fn store(mut self, store_op: vk::AttachmentStoreOp) -> Self {
+        self.store_op = store_op;
+        self
+    }
+
+    /// clears with `value` when rendering begins
+    pub fn clear(mut self, value: vk::ClearValue) -> Self {
+        self.load_op = vk::AttachmentLoadOp::CLEAR;
+        self.clear_value = value;
+        self
+    }
+
+    pub fn clear_color(self, color: [f32; 4]) -> Self {
+        self.clear(vk::ClearValue {
+            color: vk::ClearColorValue { float32: color },
+        })
+    }
+
+    pub fn info(&self) -> vk::RenderingAttachmentInfo<'static> {
+        vk::RenderingAttachmentInfo::default()
+            .image_view(self.view)
+            .image_layout(self.layout)
+            .load_op(self.load_op)
+            .store_op(self.store_op)
+            .clear_value(self.clear_value)
+    }
+}
+
+/// everything that is rendered to between `begin_rendering` and `end_rendering`
+#[derive(Clone, Default)]
+pub struct RenderingAttachments {
+    pub colors: Vec<RenderingAttachment>,
+    pub depth: Option<RenderingAttachment>,
+    pub stencil: Option<RenderingAttachment>,
+}
+
+impl RenderingAttachments {
+    pub fn color(mut self, attachment: RenderingAttachment) -> Self {
+        self.colors.push(attachment);
+        self
+    }
+
+    pub fn depth(mut self, attachment: RenderingAttachment) -> Self {
+        self.depth = Some(attachment);
+        self
+    }
+
+    pub fn stencil(mut self, attachment: RenderingAttachment) -> Self {
+        self.stencil = Some(attachment);
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.colors.is_empty() && self.depth.is_none() && self.stencil.is_none() {
+            bail!("rendering needs at least one attachment");
+        }
+
+        let null = self
+            .colors
+            .iter()
+            .chain(&self.depth)
+            .chain(&self.stencil)
+            .any(|v| v.view == vk::ImageView::null());
+        if null {
+            bail!("rendering attachments need an image view");
+        }
+
+        Ok(())
+    }
+}
+
+/// records commands inside a dynamic rendering instance, rendering ends when it is dropped
+/// derefs to the command buffer, so draws are recorded through it
+pub struct RenderingScope<'a> {
+    command_buffer: &'a CommandBuffer,
+}
+
+impl<'a> RenderingScope<'a> {
+    /// begins rendering to `attachments` in the area from the origin to `extent`
+    /// the attachments have to be in their layouts already,
+    /// needs `Device::supports_dynamic_rendering`
+    pub fn new(
+        command_buffer: &'a CommandBuffer,
+        extent: vk::Extent2D,
+        attachments: &RenderingAttachments,
+    ) -> Result<Self> {
+        if !command_buffer.device().supports_dynamic_rendering() {
+            bail!("the device doesn't support dynamic rendering");
+        }
+        attachments.validate()?;
+
+        let colors: Vec<_> = attachments.colors.iter().map(|v| v.info()).collect();
+        let depth = attachments.depth.map(|v| v.info());
+        let stencil = attachments.stencil.map(|v| v.info());
+
+        let mut info = vk::RenderingInfo::default()
+            .render_area(extent.into())
+            .layer_count(1)
+            .color_attachments(&colors);
+        if let Some(depth) = &depth {
+            info = info.depth_attachment(depth);
+        }
+        if let Some(stencil) = &stencil {
+            info = info.stencil_attachment(stencil);
+        }
+
+        command_buffer.begin_rendering(&info);
+        Ok(Self { command_buffer })
+    }
+}
+
+impl Deref for RenderingScope<'_> {
+    type Target = CommandBuffer;
+
+    fn deref(&self) -> &CommandBuffer {
+        self.command_buffer
+    }
+}
+
+impl Drop for RenderingScope<'_> {
+    fn drop(&mut self) {
+        self.command_buffer.end_rendering();
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use ash::vk;
+
+use crate::prelude::{DescriptorPool, DescriptorSetLayout, DescriptorSets, DescriptorType, Device};
+
+const INITIAL_SETS_PER_POOL: u32 = 16;
+const MAX_SETS_PER_POOL: u32 = 4096;
+
+/// how many sets the pool after one with `sets` sets gets
+pub fn next_pool_capacity(sets: u32) -> u32 {
+    (sets + sets / 2).clamp(INITIAL_SETS_PER_POOL, MAX_SETS_PER_POOL)
+}
+
+/// pool sizes for `max_sets` sets that each need `per_set` descriptors,
+/// grown so that `required` fits as well
+pub fn descriptor_pool_sizes(
+    per_set: &[vk::DescriptorPoolSize],
+    max_sets: u32,
+    required: &[DescriptorType],
+) -> Vec<vk::DescriptorPoolSize> {
+    let mut sizes: Vec<vk::DescriptorPoolSize> = per_set
+        .iter()
+        .map(|v| vk::DescriptorPoolSize {
+            ty: v.ty,
+            descriptor_count: v.descriptor_count * max_sets,
+        })
+        .collect();
+
+    let mut needed: Vec<vk::DescriptorPoolSize> = vec![];
+    for binding in required {
+        match needed.iter_mut().find(|v| v.ty == binding.ty) {
+            Some(size) => size.descriptor_count += binding.count,
+            None => needed.push(vk::DescriptorPoolSize {
+                ty: binding.ty,
+                descriptor_count: binding.count,
+            }),
+        }
+    }
+
+    for need in needed {
+        match sizes.iter_mut().find(|v| v.ty == need.ty) {
+            Some(size) => size.descriptor_count = size.descriptor_count.max(need.descriptor_count),
+            None => sizes.push(need),
+        }
+    }
+
+    sizes
+}
+
+struct PoolList {
+    // pools that might still have room, the last one is tried first
+    ready: Vec<Arc<DescriptorPool>>,
+    full: Vec<Arc<DescriptorPool>>,
+    sets_per_pool: u32,
+    flags: vk::DescriptorPoolCreateFlags,
+}
+
+impl PoolList {
+    fn new(flags: vk::DescriptorPoolCreateFlags) -> Self {
+        Self {
+            ready: vec![],
+            full: vec![],
+            sets_per_pool: INITIAL_SETS_PER_POOL,
+            flags,
+        }
+    }
+}
+
+struct State {
+    persistent: PoolList,
+    transient: PoolList,
+    // for layouts with update after bind bindings, their pools need the matching flag
+    persistent_update_after_bind: PoolList,
+    transient_update_after_bind: PoolList,
+}
+
+impl State {
+    fn list(&mut self, transient: bool, layouts: &[Arc<DescriptorSetLayout>]) -> &mut PoolList {
+        let update_after_bind = layouts.iter().any(|v| v.is_update_after_bind());
+        match (transient, update_after_bind) {
+            (false, false) => &mut self.persistent,
+            (true, false) => &mut self.transient,
+            (false, true) => &mut self.persistent_update_after_bind,
+            (true, true) => &mut self.transient_update_after_bind,
+        }
+    }
+}
+
+/// hands out descriptor sets from a growing list of pools
+/// long-lived sets are freed when they are dropped, transient sets live until `reset_transient`
+/// layouts with update after bind bindings get pools created with `UPDATE_AFTER_BIND`
+pub struct DescriptorAllocator {
+    device: Arc<Device>,
+    per_set: Vec<vk::DescriptorPoolSize>,
+    state: Mutex<State>,
+}
+
+impl DescriptorAllocator {
+    /// `per_set` is how many descriptors of each type an average set needs, new pools are sized after it
+    pub fn new(device: Arc<Device>, per_set: &[vk::DescriptorPoolSize]) -> Result<Arc<Self>> {
+        let free = vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET;
+        let update_after_bind = vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND;
+
+        Ok(Self {
+            device,
+            per_set: per_set.to_vec(),
+            state: Mutex::new(State {
+                persistent: PoolList::new(free),
+                transient: PoolList::new(vk::DescriptorPoolCreateFlags::empty()),
+                persistent_update_after_bind: PoolList::new(free | update_after_bind),
+                transient_update_after_bind: PoolList::new(update_after_bind),
+            }),
+        }
+        .into())
+    }
+
+    /// sets that go back to the allocator when they are dropped
+    pub fn allocate(
+        self: &Arc<Self>,
+        layouts: &[Arc<DescriptorSetLayout>],
+    ) -> Result<Arc<DescriptorSets>> {
+        let mut state = self.state.lock().unwrap();
+        let (pool, handle, generation) = self.allocate_in(state.list(false, layouts), layouts)?;
+
+        Ok(DescriptorSets::from_raw(pool, handle, generation, layouts, Some(self.clone())).into())
+    }
+
+    /// sets that are only valid until the next `reset_transient`,
+    /// for resources that change every frame
+    pub fn allocate_transient(
+        self: &Arc<Self>,
+        layouts: &[Arc<DescriptorSetLayout>],
+    ) -> Result<Arc<DescriptorSets>> {
+        let mut state = self.state.lock().unwrap();
+        let (pool, handle, generation) = self.allocate_in(state.list(true, layouts), layouts)?;
+
+        Ok(DescriptorSets::from_raw(pool, handle, generation, layouts, Some(self.clone())).into())
+    }
+
+    /// gives all transient sets back at once, none of them may still be in use by the GPU
+    /// call this at the start of a frame once its fence is signaled
+    pub fn reset_transient(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        for list in [&mut state.transient, &mut state.transient_update_after_bind] {
+            let full = std::mem::take(&mut list.full);
+            list.ready.extend(full);
+
+            for pool in &list.ready {
+                pool.reset()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the number of pools that were created so far
+    pub fn pool_count(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        [
+            &state.persistent,
+            &state.transient,
+            &state.persistent_update_after_bind,
+            &state.transient_update_after_bind,
+        ]
+        .iter()
+        .map(|v| v.ready.len() + v.full.len())
+        .sum()
+    }
+
+    fn allocate_in(
+        &self,
+        list: &mut PoolList,
+        layouts: &[Arc<DescriptorSetLayout>],
+    ) -> Result<(Arc<DescriptorPool>, Vec<vk::DescriptorSet>, u64)> {
+        let raw_layouts: Vec<_> = layouts.iter().map(|v| *v.as_raw()).collect();
+
+        while let Some(pool) = list.ready.last() {
+            match pool.allocate(&raw_layouts) {
+                Ok((sets, generation)) => return Ok((pool.clone(), sets, generation)),
+                Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY | vk::Result::ERROR_FRAGMENTED_POOL) => {
+                    let pool = list.ready.pop().unwrap();
+                    list.full.push(pool);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let required: Vec<_> = layouts
+            .iter()
+            .flat_map(|v| v.bindings().iter().copied())
+            .collect();
+        let max_sets = list.sets_per_pool.max(layouts.len() as u32);
+        let sizes = descriptor_pool_sizes(&self.per_set, max_sets, &required);
+
+        let pool = DescriptorPool::with_sizes(self.device.clone(), &sizes, max_sets, list.flags)?;
+        list.sets_per_pool = next_pool_capacity(list.sets_per_pool);
+
+        let (sets, generation) = pool
+            .allocate(&raw_layouts)
+            .context("a new descriptor pool can't fit the sets")?;
+        list.ready.push(pool.clone());
+
+        Ok((pool, sets, generation))
+    }
+
+    /// frees long-lived sets, their pool is tried again for the next allocations
+    pub(crate) fn free(
+        &self,
+        pool: &Arc<DescriptorPool>,
+        sets: &[vk::DescriptorSet],
+        generation: u64,
+    ) -> Result<()> {
+        pool.free(sets, generation)?;
+
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        for list in [
+            &mut state.persistent,
+            &mut state.persistent_update_after_bind,
+        ] {
+            if let Some(index) = list.full.iter().position(|v| Arc::ptr_eq(v, pool)) {
+                let pool = list.full.remove(index);
+                list.ready.push(pool);
+            }
+        }
+
+        Ok(())
+    }
+}
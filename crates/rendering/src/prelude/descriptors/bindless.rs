@@ -0,0 +1,274 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::{
+    BufferAllocation, DescriptorPool, DescriptorSetLayout, DescriptorSets, DescriptorType,
+    DescriptorWrite, Device, ImageView, Sampler,
+};
+
+/// binding of the sampled image array in the bindless set
+pub const BINDLESS_IMAGE_BINDING: u32 = 0;
+/// binding of the storage buffer array in the bindless set
+pub const BINDLESS_BUFFER_BINDING: u32 = 1;
+/// binding of the sampler array in the bindless set
+pub const BINDLESS_SAMPLER_BINDING: u32 = 2;
+
+/// hands out indices below `capacity`, freed indices are reused first
+#[derive(Debug, Clone)]
+pub struct SlotAllocator {
+    free: Vec<u32>,
+    next: u32,
+    capacity: u32,
+}
+
+impl SlotAllocator {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            free: vec![],
+            next: 0,
+            capacity,
+        }
+    }
+
+    pub fn allocate(&mut self) -> Option<u32> {
+        if let Some(index) = self.free.pop() {
+            return Some(index);
+        }
+
+        if self.next == self.capacity {
+            return None;
+        }
+        self.next += 1;
+        Some(self.next - 1)
+    }
+
+    /// fails if `index` isn't in use
+    pub fn free(&mut self, index: u32) -> Result<()> {
+        if index >= self.next || self.free.contains(&index) {
+            bail!("slot {index} isn't in use");
+        }
+        self.free.push(index);
+        Ok(())
+    }
+
+    /// the number of slots in use
+    pub fn len(&self) -> u32 {
+        self.next - self.free.len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+}
+
+/// the array sizes of a `BindlessHeap`, they have to fit the update after bind limits of the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindlessCapacity {
+    pub images: u32,
+    pub buffers: u32,
+    pub samplers: u32,
+}
+
+impl BindlessCapacity {
+    /// fails with the name of the first update after bind limit the arrays don't fit into
+    /// every array is visible to all stages, so the per stage limits apply as well
+    pub fn check_limits(
+        &self,
+        limits: &vk::PhysicalDeviceDescriptorIndexingProperties,
+    ) -> Result<()> {
+        let checks = [
+            (
+                "maxPerStageDescriptorUpdateAfterBindSampledImages",
+                self.images,
+                limits.max_per_stage_descriptor_update_after_bind_sampled_images,
+            ),
+            (
+                "maxPerStageDescriptorUpdateAfterBindStorageBuffers",
+                self.buffers,
+                limits.max_per_stage_descriptor_update_after_bind_storage_buffers,
+            ),
+            (
+                "maxPerStageDescriptorUpdateAfterBindSamplers",
+                self.samplers,
+                limits.max_per_stage_descriptor_update_after_bind_samplers,
+            ),
+            (
+                "maxDescriptorSetUpdateAfterBindSampledImages",
+                self.images,
+                limits.max_descriptor_set_update_after_bind_sampled_images,
+            ),
+            (
+                "maxDescriptorSetUpdateAfterBindStorageBuffers",
+                self.buffers,
+                limits.max_descriptor_set_update_after_bind_storage_buffers,
+            ),
+            (
+                "maxDescriptorSetUpdateAfterBindSamplers",
+                self.samplers,
+                limits.max_descriptor_set_update_after_bind_samplers,
+            ),
+            (
+                "maxPerStageUpdateAfterBindResources",
+                self.images
+                    .saturating_add(self.buffers)
+                    .saturating_add(self.samplers),
+                limits.max_per_stage_update_after_bind_resources,
+            ),
+        ];
+
+        for (name, count, limit) in checks {
+            if count > limit {
+                bail!("the bindless heap needs {count} descriptors but {name} is {limit}");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for BindlessCapacity {
+    fn default() -> Self {
+        Self {
+            images: 4096,
+            buffers: 4096,
+            samplers: 64,
+        }
+    }
+}
+
+/// one global descriptor set with arrays of sampled images, storage buffers and samplers
+/// that shaders index into, bound with `CommandBuffer::bind_descriptor_sets`
+/// entries can be added and removed while the set is bound, indices stay the same until they are removed
+pub struct BindlessHeap {
+    layout: Arc<DescriptorSetLayout>,
+    sets: Arc<DescriptorSets>,
+    images: Mutex<SlotAllocator>,
+    buffers: Mutex<SlotAllocator>,
+    samplers: Mutex<SlotAllocator>,
+}
+
+impl BindlessHeap {
+    /// needs `Device::supports_descriptor_indexing`
+    pub fn new(device: Arc<Device>, capacity: BindlessCapacity) -> Result<Arc<Self>> {
+        if !device.supports_descriptor_indexing() {
+            bail!("the device doesn't support the descriptor indexing features bindless needs");
+        }
+        capacity.check_limits(&device.descriptor_indexing_properties())?;
+
+        let binding = |binding: u32, ty: vk::DescriptorType, count: u32| DescriptorType {
+            ty,
+            stage_flags: vk::ShaderStageFlags::ALL,
+            count,
+            binding,
+        };
+        let descriptors = [
+            binding(
+                BINDLESS_IMAGE_BINDING,
+                vk::DescriptorType::SAMPLED_IMAGE,
+                capacity.images,
+            ),
+            binding(
+                BINDLESS_BUFFER_BINDING,
+                vk::DescriptorType::STORAGE_BUFFER,
+                capacity.buffers,
+            ),
+            binding(
+                BINDLESS_SAMPLER_BINDING,
+                vk::DescriptorType::SAMPLER,
+                capacity.samplers,
+            ),
+        ];
+        let flags = vk::DescriptorBindingFlags::PARTIALLY_BOUND
+            | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+            | vk::DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING;
+
+        let layout =
+            DescriptorSetLayout::with_binding_flags(device.clone(), &descriptors, &[flags; 3])?;
+
+        let sizes: Vec<vk::DescriptorPoolSize> = descriptors.iter().map(|v| (*v).into()).collect();
+        let pool = DescriptorPool::with_sizes(
+            device,
+            &sizes,
+            1,
+            vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND,
+        )?;
+        let sets = DescriptorSets::new(pool, std::slice::from_ref(&layout))?;
+
+        Ok(Self {
+            layout,
+            sets,
+            images: Mutex::new(SlotAllocator::new(capacity.images)),
+            buffers: Mutex::new(SlotAllocator::new(capacity.buffers)),
+            samplers: Mutex::new(SlotAllocator::new(capacity.samplers)),
+        }
+        .into())
+    }
+
+    fn add(
+        &self,
+        slots: &Mutex<SlotAllocator>,
+        write: impl FnOnce(u32) -> DescriptorWrite,
+    ) -> Result<u32> {
+        let mut slots = slots.lock().unwrap();
+        let Some(index) = slots.allocate() else {
+            bail!("all {} bindless slots are in use", slots.capacity());
+        };
+
+        if let Err(err) = self.sets.write(0, &[write(index)]) {
+            slots.free(index)?;
+            return Err(err);
+        }
+
+        Ok(index)
+    }
+
+    /// the image needs `ImageUsageFlags::SAMPLED` and has to be in `layout` when shaders read it
+    pub fn add_image(&self, view: Arc<ImageView>, layout: vk::ImageLayout) -> Result<u32> {
+        self.add(&self.images, |index| {
+            DescriptorWrite::sampled_image(BINDLESS_IMAGE_BINDING, view, layout)
+                .array_element(index)
+        })
+    }
+
+    /// the buffer needs `BufferUsageFlags::STORAGE_BUFFER`
+    pub fn add_buffer(&self, buffer: Arc<dyn BufferAllocation>) -> Result<u32> {
+        self.add(&self.buffers, |index| {
+            DescriptorWrite::storage_buffer(BINDLESS_BUFFER_BINDING, buffer).array_element(index)
+        })
+    }
+
+    pub fn add_sampler(&self, sampler: Arc<Sampler>) -> Result<u32> {
+        self.add(&self.samplers, |index| {
+            DescriptorWrite::sampler(BINDLESS_SAMPLER_BINDING, sampler).array_element(index)
+        })
+    }
+
+    /// the slot is reused by the next `add_image`, so it must not be used by pending GPU work anymore
+    /// the image stays alive until the slot is overwritten
+    pub fn remove_image(&self, index: u32) -> Result<()> {
+        self.images.lock().unwrap().free(index)
+    }
+
+    /// see `remove_image`
+    pub fn remove_buffer(&self, index: u32) -> Result<()> {
+        self.buffers.lock().unwrap().free(index)
+    }
+
+    /// see `remove_image`
+    pub fn remove_sampler(&self, index: u32) -> Result<()> {
+        self.samplers.lock().unwrap().free(index)
+    }
+
+    pub fn layout(&self) -> &Arc<DescriptorSetLayout> {
+        &self.layout
+    }
+
+    pub fn sets(&self) -> &Arc<DescriptorSets> {
+        &self.sets
+    }
+}
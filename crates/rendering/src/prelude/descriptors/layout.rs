@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use ash::vk;
 use std::sync::Arc;
 
@@ -7,18 +7,74 @@ use crate::prelude::{DescriptorType, Device};
 pub struct DescriptorSetLayout {
     handle: vk::DescriptorSetLayout,
     device: Arc<Device>,
+    bindings: Vec<DescriptorType>,
+    binding_flags: Vec<vk::DescriptorBindingFlags>,
 }
 
 impl DescriptorSetLayout {
     pub fn new(device: Arc<Device>, decriptors: &[DescriptorType]) -> Result<Arc<Self>> {
+        Self::with_binding_flags(device, decriptors, &[])
+    }
+
+    /// `binding_flags` has one entry per descriptor or is empty
+    /// sets of layouts with `DescriptorBindingFlags::UPDATE_AFTER_BIND` bindings need a pool with
+    /// `DescriptorPoolCreateFlags::UPDATE_AFTER_BIND`, `DescriptorAllocator` creates those itself
+    pub fn with_binding_flags(
+        device: Arc<Device>,
+        decriptors: &[DescriptorType],
+        binding_flags: &[vk::DescriptorBindingFlags],
+    ) -> Result<Arc<Self>> {
+        if !binding_flags.is_empty() && binding_flags.len() != decriptors.len() {
+            bail!(
+                "got {} binding flags for {} descriptors",
+                binding_flags.len(),
+                decriptors.len()
+            );
+        }
+
         let bindings: Vec<vk::DescriptorSetLayoutBinding> =
-            decriptors.into_iter().map(|v| (*v).into()).collect();
+            decriptors.iter().map(|v| (*v).into()).collect();
+
+        let update_after_bind = binding_flags
+            .iter()
+            .any(|v| v.contains(vk::DescriptorBindingFlags::UPDATE_AFTER_BIND));
+        let flags = if update_after_bind {
+            vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL
+        } else {
+            vk::DescriptorSetLayoutCreateFlags::empty()
+        };
 
-        let info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+        let mut flags_info =
+            vk::DescriptorSetLayoutBindingFlagsCreateInfo::default().binding_flags(binding_flags);
+        let info = vk::DescriptorSetLayoutCreateInfo::default()
+            .bindings(&bindings)
+            .flags(flags);
+        let info = if binding_flags.is_empty() {
+            info
+        } else {
+            info.push_next(&mut flags_info)
+        };
 
         let handle = unsafe { device.as_raw().create_descriptor_set_layout(&info, None) }?;
 
-        Ok(Self { handle, device }.into())
+        Ok(Self {
+            handle,
+            device,
+            bindings: decriptors.to_vec(),
+            binding_flags: binding_flags.to_vec(),
+        }
+        .into())
+    }
+
+    /// true if the layout has bindings that can be updated after the sets are bound
+    pub fn is_update_after_bind(&self) -> bool {
+        self.binding_flags
+            .iter()
+            .any(|v| v.contains(vk::DescriptorBindingFlags::UPDATE_AFTER_BIND))
+    }
+
+    pub fn bindings(&self) -> &[DescriptorType] {
+        &self.bindings
     }
 
     pub fn as_raw(&self) -> &vk::DescriptorSetLayout {
@@ -1,16 +1,22 @@
 use ash::vk;
 
+mod allocator;
+mod bindless;
 mod layout;
 mod pool;
 mod sets;
+mod write;
 
+pub use allocator::*;
+pub use bindless::*;
 pub use layout::*;
 pub use pool::*;
 pub use sets::*;
+pub use write::*;
 pub use vk::{DescriptorType as VkDescriptorType, ShaderStageFlags};
 
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct DescriptorType {
     pub ty: vk::DescriptorType,
     pub stage_flags: vk::ShaderStageFlags,
@@ -1,24 +1,102 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use crate::prelude::{DescriptorType, Device};
 use anyhow::Result;
-use ash::vk;
+use ash::{prelude::VkResult, vk};
 
 #[allow(unused)]
 pub struct DescriptorPool {
     handle: vk::DescriptorPool,
     device: Arc<Device>,
+    flags: vk::DescriptorPoolCreateFlags,
+    // counts the resets, sets from an older generation are gone
+    // the lock also synchronizes allocating, freeing and resetting
+    generation: Mutex<u64>,
 }
 
 impl DescriptorPool {
+    /// a pool with room for one set with the bindings in `sizes`
     pub fn new(device: Arc<Device>, sizes: &[DescriptorType]) -> Result<Arc<Self>> {
         let sizes: Vec<vk::DescriptorPoolSize> = sizes.into_iter().map(|v| (*v).into()).collect();
 
-        let info = vk::DescriptorPoolCreateInfo::default().pool_sizes(&sizes);
+        Self::with_sizes(device, &sizes, 1, vk::DescriptorPoolCreateFlags::empty())
+    }
+
+    /// sets from pools with `DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET` are freed when they are dropped
+    pub fn with_sizes(
+        device: Arc<Device>,
+        sizes: &[vk::DescriptorPoolSize],
+        max_sets: u32,
+        flags: vk::DescriptorPoolCreateFlags,
+    ) -> Result<Arc<Self>> {
+        let info = vk::DescriptorPoolCreateInfo::default()
+            .pool_sizes(sizes)
+            .max_sets(max_sets)
+            .flags(flags);
 
         let handle = unsafe { device.as_raw().create_descriptor_pool(&info, None) }?;
 
-        Ok(Self { handle, device }.into())
+        Ok(Self {
+            handle,
+            device,
+            flags,
+            generation: Mutex::new(0),
+        }
+        .into())
+    }
+
+    /// returns the sets and the generation they belong to
+    pub(crate) fn allocate(
+        &self,
+        layouts: &[vk::DescriptorSetLayout],
+    ) -> VkResult<(Vec<vk::DescriptorSet>, u64)> {
+        let generation = self.generation.lock().unwrap();
+
+        let info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(self.handle)
+            .set_layouts(layouts);
+
+        let sets = unsafe { self.device.as_raw().allocate_descriptor_sets(&info) }?;
+
+        Ok((sets, *generation))
+    }
+
+    /// does nothing if the pool was reset since the sets were allocated
+    pub(crate) fn free(&self, sets: &[vk::DescriptorSet], generation: u64) -> Result<()> {
+        let current = self.generation.lock().unwrap();
+        if *current != generation || !self.can_free() {
+            return Ok(());
+        }
+
+        unsafe {
+            self.device
+                .as_raw()
+                .free_descriptor_sets(self.handle, sets)
+        }?;
+        Ok(())
+    }
+
+    /// gives all sets back to the pool, none of them may be in use by the GPU
+    pub fn reset(&self) -> Result<()> {
+        let mut generation = self.generation.lock().unwrap();
+
+        unsafe {
+            self.device
+                .as_raw()
+                .reset_descriptor_pool(self.handle, vk::DescriptorPoolResetFlags::empty())
+        }?;
+
+        *generation += 1;
+        Ok(())
+    }
+
+    pub fn generation(&self) -> u64 {
+        *self.generation.lock().unwrap()
+    }
+
+    pub fn can_free(&self) -> bool {
+        self.flags
+            .contains(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
     }
 
     pub fn device(&self) -> Arc<Device> {
@@ -27,7 +105,6 @@ impl DescriptorPool {
     pub fn as_raw(&self) -> &vk::DescriptorPool {
         &self.handle
     }
-    
 }
 
 impl Drop for DescriptorPool {
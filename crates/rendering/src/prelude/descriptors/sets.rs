@@ -1,27 +1,140 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use ash::vk;
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
-use crate::prelude::{DescriptorPool, DescriptorSetLayout};
+use crate::prelude::{
+    DescriptorAllocator, DescriptorPool, DescriptorResource, DescriptorSetLayout, DescriptorWrite,
+    GpuUse,
+};
 
 #[allow(unused)]
 pub struct DescriptorSets {
     handle: Vec<vk::DescriptorSet>,
     pool: Arc<DescriptorPool>,
+    // the pool generation the sets were allocated in
+    generation: u64,
+    // where the sets go back to when they are dropped
+    allocator: Option<Arc<DescriptorAllocator>>,
+    layouts: Vec<Arc<DescriptorSetLayout>>,
+    // the resources written into the sets, keyed by (set, binding, array element)
+    resources: Mutex<HashMap<(usize, u32, u32), DescriptorResource>>,
 }
 
 impl DescriptorSets {
     pub fn new(pool: Arc<DescriptorPool>, layouts: &[Arc<DescriptorSetLayout>]) -> Result<Arc<Self>> {
-        let device = pool.device();
-
         let raw_layouts: Vec<_> = layouts.into_iter().map(|v| *v.as_raw()).collect();
 
-        let alloc_info = vk::DescriptorSetAllocateInfo::default()
-            .descriptor_pool(*pool.as_raw())
-            .set_layouts(&raw_layouts);
+        let (handle, generation) = pool.allocate(&raw_layouts)?;
+
+        Ok(Self::from_raw(pool, handle, generation, layouts, None).into())
+    }
+
+    pub(crate) fn from_raw(
+        pool: Arc<DescriptorPool>,
+        handle: Vec<vk::DescriptorSet>,
+        generation: u64,
+        layouts: &[Arc<DescriptorSetLayout>],
+        allocator: Option<Arc<DescriptorAllocator>>,
+    ) -> Self {
+        Self {
+            handle,
+            pool,
+            generation,
+            allocator,
+            layouts: layouts.to_vec(),
+            resources: Mutex::default(),
+        }
+    }
+
+    /// false once the pool the sets came from was reset
+    pub fn is_valid(&self) -> bool {
+        self.pool.generation() == self.generation
+    }
+
+    /// writes resources into the set at index `set`
+    /// the sets keep the resources alive until they get overwritten,
+    /// sets that are in use by the GPU must not be written
+    /// nothing is written if any of the writes doesn't match the layout of the set
+    pub fn write(&self, set: usize, writes: &[DescriptorWrite]) -> Result<()> {
+        let Some(&handle) = self.handle.get(set) else {
+            bail!("there are only {} descriptor sets", self.handle.len());
+        };
+        if !self.is_valid() {
+            bail!("the descriptor pool of the sets was reset");
+        }
+
+        let bindings = self.layouts[set].bindings();
+        for write in writes {
+            write
+                .validate(bindings)
+                .with_context(|| format!("invalid write to descriptor set {set}"))?;
+        }
+
+        // the infos have to stay in place until the update, so they are collected first
+        let buffer_infos: Vec<_> = writes.iter().map(|v| v.resource.buffer_info()).collect();
+        let image_infos: Vec<_> = writes.iter().map(|v| v.resource.image_info()).collect();
 
-        let handle = unsafe { device.as_raw().allocate_descriptor_sets(&alloc_info) }?;
+        let raw_writes: Vec<_> = writes
+            .iter()
+            .zip(buffer_infos.iter().zip(&image_infos))
+            .map(|(write, (buffer_info, image_info))| {
+                let raw = vk::WriteDescriptorSet::default()
+                    .dst_set(handle)
+                    .dst_binding(write.binding)
+                    .dst_array_element(write.array_element)
+                    .descriptor_type(write.resource.descriptor_type());
+
+                match (buffer_info, image_info) {
+                    (Some(info), _) => raw.buffer_info(std::slice::from_ref(info)),
+                    (_, Some(info)) => raw.image_info(std::slice::from_ref(info)),
+                    _ => raw,
+                }
+            })
+            .collect();
+
+        unsafe {
+            self.pool
+                .device()
+                .as_raw()
+                .update_descriptor_sets(&raw_writes, &[])
+        };
+
+        let mut resources = self.resources.lock().unwrap();
+        for write in writes {
+            resources.insert(
+                (set, write.binding, write.array_element),
+                write.resource.clone(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// tokens for the tracked buffers written into the sets
+    pub(crate) fn gpu_uses(&self) -> Vec<GpuUse> {
+        self.resources
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(DescriptorResource::gpu_use)
+            .collect()
+    }
+
+    pub fn as_raw(&self) -> &[vk::DescriptorSet] {
+        &self.handle
+    }
+}
 
-        Ok(Self { handle, pool }.into())
+impl Drop for DescriptorSets {
+    fn drop(&mut self) {
+        // sets from pools that can't free them go back when the pool is reset
+        let result = match &self.allocator {
+            Some(allocator) => allocator.free(&self.pool, &self.handle, self.generation),
+            None => self.pool.free(&self.handle, self.generation),
+        };
+        result.ok();
     }
 }
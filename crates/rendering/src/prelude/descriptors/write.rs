@@ -0,0 +1,228 @@
+use std::{ops::Range, sync::Arc};
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::{BufferAllocation, DescriptorType, GpuUse, ImageView, Sampler};
+
+/// what gets written into a descriptor, the `DescriptorSets` keeps it alive
+#[derive(Clone)]
+pub enum DescriptorResource {
+    UniformBuffer {
+        buffer: Arc<dyn BufferAllocation>,
+        /// relative to the start of `buffer`
+        range: Range<u64>,
+    },
+    StorageBuffer {
+        buffer: Arc<dyn BufferAllocation>,
+        range: Range<u64>,
+    },
+    SampledImage {
+        view: Arc<ImageView>,
+        layout: vk::ImageLayout,
+    },
+    CombinedImageSampler {
+        view: Arc<ImageView>,
+        sampler: Arc<Sampler>,
+        layout: vk::ImageLayout,
+    },
+    /// storage images are always accessed in `ImageLayout::GENERAL`
+    StorageImage {
+        view: Arc<ImageView>,
+    },
+    Sampler(Arc<Sampler>),
+}
+
+impl DescriptorResource {
+    pub fn descriptor_type(&self) -> vk::DescriptorType {
+        match self {
+            Self::UniformBuffer { .. } => vk::DescriptorType::UNIFORM_BUFFER,
+            Self::StorageBuffer { .. } => vk::DescriptorType::STORAGE_BUFFER,
+            Self::SampledImage { .. } => vk::DescriptorType::SAMPLED_IMAGE,
+            Self::CombinedImageSampler { .. } => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            Self::StorageImage { .. } => vk::DescriptorType::STORAGE_IMAGE,
+            Self::Sampler(_) => vk::DescriptorType::SAMPLER,
+        }
+    }
+
+    pub(crate) fn buffer_info(&self) -> Option<vk::DescriptorBufferInfo> {
+        let (Self::UniformBuffer { buffer, range } | Self::StorageBuffer { buffer, range }) = self
+        else {
+            return None;
+        };
+
+        Some(vk::DescriptorBufferInfo {
+            buffer: buffer.buffer(),
+            offset: buffer.offset() + range.start,
+            range: range.end - range.start,
+        })
+    }
+
+    pub(crate) fn gpu_use(&self) -> Option<GpuUse> {
+        match self {
+            Self::UniformBuffer { buffer, .. } | Self::StorageBuffer { buffer, .. } => {
+                buffer.gpu_use()
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn image_info(&self) -> Option<vk::DescriptorImageInfo> {
+        let info = vk::DescriptorImageInfo::default();
+
+        Some(match self {
+            Self::SampledImage { view, layout } => {
+                info.image_view(*view.as_raw()).image_layout(*layout)
+            }
+            Self::CombinedImageSampler {
+                view,
+                sampler,
+                layout,
+            } => info
+                .image_view(*view.as_raw())
+                .sampler(*sampler.as_raw())
+                .image_layout(*layout),
+            Self::StorageImage { view } => info
+                .image_view(*view.as_raw())
+                .image_layout(vk::ImageLayout::GENERAL),
+            Self::Sampler(sampler) => info.sampler(*sampler.as_raw()),
+            _ => return None,
+        })
+    }
+}
+
+/// one descriptor update for `DescriptorSets::write`
+#[derive(Clone)]
+pub struct DescriptorWrite {
+    pub binding: u32,
+    /// index into the descriptor array of the binding
+    pub array_element: u32,
+    pub resource: DescriptorResource,
+}
+
+impl DescriptorWrite {
+    pub fn new(binding: u32, resource: DescriptorResource) -> Self {
+        Self {
+            binding,
+            array_element: 0,
+            resource,
+        }
+    }
+
+    /// the whole buffer
+    pub fn uniform_buffer(binding: u32, buffer: Arc<dyn BufferAllocation>) -> Self {
+        let range = 0..buffer.size();
+        Self::new(binding, DescriptorResource::UniformBuffer { buffer, range })
+    }
+
+    /// the whole buffer
+    pub fn storage_buffer(binding: u32, buffer: Arc<dyn BufferAllocation>) -> Self {
+        let range = 0..buffer.size();
+        Self::new(binding, DescriptorResource::StorageBuffer { buffer, range })
+    }
+
+    pub fn sampled_image(binding: u32, view: Arc<ImageView>, layout: vk::ImageLayout) -> Self {
+        Self::new(binding, DescriptorResource::SampledImage { view, layout })
+    }
+
+    pub fn combined_image_sampler(
+        binding: u32,
+        view: Arc<ImageView>,
+        sampler: Arc<Sampler>,
+        layout: vk::ImageLayout,
+    ) -> Self {
+        Self::new(
+            binding,
+            DescriptorResource::CombinedImageSampler {
+                view,
+                sampler,
+                layout,
+            },
+        )
+    }
+
+    pub fn storage_image(binding: u32, view: Arc<ImageView>) -> Self {
+        Self::new(binding, DescriptorResource::StorageImage { view })
+    }
+
+    pub fn sampler(binding: u32, sampler: Arc<Sampler>) -> Self {
+        Self::new(binding, DescriptorResource::Sampler(sampler))
+    }
+
+    pub fn array_element(mut self, array_element: u32) -> Self {
+        self.array_element = array_element;
+        self
+    }
+
+    /// checks the write against the bindings of the layout it goes into
+    pub fn validate(&self, bindings: &[DescriptorType]) -> Result<()> {
+        let Some(binding) = bindings.iter().find(|v| v.binding == self.binding) else {
+            bail!("the layout has no binding {}", self.binding);
+        };
+
+        let ty = self.resource.descriptor_type();
+        if binding.ty != ty {
+            bail!(
+                "binding {} is a {:?} descriptor but a {:?} was written",
+                self.binding,
+                binding.ty,
+                ty
+            );
+        }
+
+        if self.array_element >= binding.count {
+            bail!(
+                "array element {} is out of range for binding {} with {} descriptors",
+                self.array_element,
+                self.binding,
+                binding.count
+            );
+        }
+
+        match &self.resource {
+            DescriptorResource::UniformBuffer { buffer, range }
+            | DescriptorResource::StorageBuffer { buffer, range } => {
+                let usage = match ty {
+                    vk::DescriptorType::UNIFORM_BUFFER => vk::BufferUsageFlags::UNIFORM_BUFFER,
+                    _ => vk::BufferUsageFlags::STORAGE_BUFFER,
+                };
+                if !buffer.usage().contains(usage) {
+                    bail!(
+                        "buffer written to binding {} needs {:?} usage but has {:?}",
+                        self.binding,
+                        usage,
+                        buffer.usage()
+                    );
+                }
+
+                if range.is_empty() || range.end > buffer.size() {
+                    bail!(
+                        "range {:?} written to binding {} doesn't fit a buffer of {} bytes",
+                        range,
+                        self.binding,
+                        buffer.size()
+                    );
+                }
+            }
+            DescriptorResource::SampledImage { view, .. }
+            | DescriptorResource::CombinedImageSampler { view, .. }
+            | DescriptorResource::StorageImage { view } => {
+                let usage = match ty {
+                    vk::DescriptorType::STORAGE_IMAGE => vk::ImageUsageFlags::STORAGE,
+                    _ => vk::ImageUsageFlags::SAMPLED,
+                };
+                if !view.usage().contains(usage) {
+                    bail!(
+                        "image written to binding {} needs {:?} usage but has {:?}",
+                        self.binding,
+                        usage,
+                        view.usage()
+                    );
+                }
+            }
+            DescriptorResource::Sampler(_) => {}
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    ffi::{c_char, CStr, CString},
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::{Device, Instance, Surface};
+
+/// what `DeviceBuilder` knows about a physical device
+#[derive(Clone)]
+pub struct PhysicalDeviceInfo {
+    /// position in `vkEnumeratePhysicalDevices`
+    pub index: usize,
+    pub handle: vk::PhysicalDevice,
+    pub properties: vk::PhysicalDeviceProperties,
+    pub features: vk::PhysicalDeviceFeatures,
+    pub extensions: Vec<CString>,
+    pub queue_families: Vec<vk::QueueFamilyProperties>,
+}
+
+impl PhysicalDeviceInfo {
+    pub fn name(&self) -> String {
+        self.properties
+            .device_name_as_c_str()
+            .map(|v| v.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn supports_extension(&self, name: &CStr) -> bool {
+        self.extensions.iter().any(|v| v.as_c_str() == name)
+    }
+}
+
+/// the queue family used for every kind of work, families can be the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFamilies {
+    pub graphics: u32,
+    pub compute: u32,
+    pub transfer: u32,
+    /// only chosen if the device was built for a surface
+    pub present: Option<u32>,
+}
+
+impl QueueFamilies {
+    /// prefers dedicated compute and transfer families and presenting from the graphics family
+    /// `supports_present` is only asked if `need_present` is set
+    pub fn select(
+        families: &[vk::QueueFamilyProperties],
+        need_present: bool,
+        supports_present: impl Fn(u32) -> bool,
+    ) -> Option<Self> {
+        let with = |required: vk::QueueFlags, excluded: vk::QueueFlags| {
+            families
+                .iter()
+                .position(|v| {
+                    v.queue_count > 0
+                        && v.queue_flags.contains(required)
+                        && !v.queue_flags.intersects(excluded)
+                })
+                .map(|v| v as u32)
+        };
+        use vk::QueueFlags as Q;
+
+        let graphics = with(Q::GRAPHICS, Q::empty())?;
+        let compute = with(Q::COMPUTE, Q::GRAPHICS).unwrap_or(graphics);
+        // every graphics or compute family can do transfers, even if it doesn't say so
+        let transfer = with(Q::TRANSFER, Q::GRAPHICS | Q::COMPUTE)
+            .or_else(|| with(Q::TRANSFER, Q::GRAPHICS))
+            .unwrap_or(graphics);
+
+        let present = if need_present {
+            let present = if supports_present(graphics) {
+                graphics
+            } else {
+                (0..families.len() as u32).find(|&v| supports_present(v))?
+            };
+            Some(present)
+        } else {
+            None
+        };
+
+        Some(Self {
+            graphics,
+            compute,
+            transfer,
+            present,
+        })
+    }
+
+    /// every family once, in the order graphics, compute, transfer, present
+    pub fn unique(&self) -> Vec<u32> {
+        let mut families = vec![];
+        for family in [self.graphics, self.compute, self.transfer]
+            .into_iter()
+            .chain(self.present)
+        {
+            if !families.contains(&family) {
+                families.push(family);
+            }
+        }
+        families
+    }
+}
+
+/// higher is better, discrete GPUs first and CPU implementations last
+pub fn score_physical_device(properties: &vk::PhysicalDeviceProperties) -> u64 {
+    let kind = match properties.device_type {
+        vk::PhysicalDeviceType::DISCRETE_GPU => 4,
+        vk::PhysicalDeviceType::INTEGRATED_GPU => 3,
+        vk::PhysicalDeviceType::VIRTUAL_GPU => 2,
+        vk::PhysicalDeviceType::CPU => 1,
+        _ => 0,
+    };
+
+    // devices of the same kind are compared by their biggest texture size
+    kind << 32 | properties.limits.max_image_dimension2_d as u64
+}
+
+fn feature_flags(features: &vk::PhysicalDeviceFeatures) -> &[vk::Bool32] {
+    // `PhysicalDeviceFeatures` is nothing but `Bool32`s
+    unsafe {
+        std::slice::from_raw_parts(
+            (features as *const vk::PhysicalDeviceFeatures).cast::<vk::Bool32>(),
+            std::mem::size_of::<vk::PhysicalDeviceFeatures>() / std::mem::size_of::<vk::Bool32>(),
+        )
+    }
+}
+
+/// true if every feature enabled in `required` is in `supported`
+pub fn supports_features(
+    required: &vk::PhysicalDeviceFeatures,
+    supported: &vk::PhysicalDeviceFeatures,
+) -> bool {
+    feature_flags(required)
+        .iter()
+        .zip(feature_flags(supported))
+        .all(|(required, supported)| *required == vk::FALSE || *supported == vk::TRUE)
+}
+
+type DeviceFilter = Box<dyn Fn(&PhysicalDeviceInfo) -> bool>;
+
+/// picks a physical device and its queue families and creates the `Device`
+/// without a filter or index the suitable device with the highest `score_physical_device` wins
+pub struct DeviceBuilder {
+    instance: Arc<Instance>,
+    surface: Option<Arc<Surface>>,
+    extensions: Vec<&'static CStr>,
+    features: vk::PhysicalDeviceFeatures,
+    filter: Option<DeviceFilter>,
+    index: Option<usize>,
+}
+
+impl DeviceBuilder {
+    /// requires `shaderClipDistance` and nothing else
+    pub fn new(instance: Arc<Instance>) -> Self {
+        Self {
+            instance,
+            surface: None,
+            extensions: vec![],
+            features: vk::PhysicalDeviceFeatures {
+                shader_clip_distance: vk::TRUE,
+                ..Default::default()
+            },
+            filter: None,
+            index: None,
+        }
+    }
+
+    /// the device has to be able to present to `surface`, this also requires `VK_KHR_swapchain`
+    pub fn surface(mut self, surface: Arc<Surface>) -> Self {
+        self.surface = Some(surface);
+        self.extension(ash::khr::swapchain::NAME)
+    }
+
+    /// a device extension the device has to support, it gets enabled
+    pub fn extension(mut self, name: &'static CStr) -> Self {
+        if !self.extensions.contains(&name) {
+            self.extensions.push(name);
+        }
+        self
+    }
+
+    /// the features the device has to support, they get enabled
+    pub fn features(mut self, features: vk::PhysicalDeviceFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// only devices `filter` returns true for are considered
+    pub fn filter(mut self, filter: impl Fn(&PhysicalDeviceInfo) -> bool + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// uses the device at `index` in `physical_devices`, fails if it doesn't fit the requirements
+    pub fn index(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// every physical device of the instance
+    pub fn physical_devices(instance: &Instance) -> Result<Vec<PhysicalDeviceInfo>> {
+        let raw = instance.as_raw();
+        let handles = unsafe { raw.enumerate_physical_devices() }?;
+
+        handles
+            .into_iter()
+            .enumerate()
+            .map(|(index, handle)| unsafe {
+                let extensions = raw
+                    .enumerate_device_extension_properties(handle)?
+                    .iter()
+                    .filter_map(|v| v.extension_name_as_c_str().ok().map(CStr::to_owned))
+                    .collect();
+
+                Ok(PhysicalDeviceInfo {
+                    index,
+                    handle,
+                    properties: raw.get_physical_device_properties(handle),
+                    features: raw.get_physical_device_features(handle),
+                    extensions,
+                    queue_families: raw.get_physical_device_queue_family_properties(handle),
+                })
+            })
+            .collect()
+    }
+
+    /// the queue families to use, or why the device doesn't fit
+    fn check(&self, info: &PhysicalDeviceInfo) -> Result<QueueFamilies, String> {
+        if let Some(extension) = self.extensions.iter().find(|v| !info.supports_extension(v)) {
+            return Err(format!("missing extension {}", extension.to_string_lossy()));
+        }
+
+        if !supports_features(&self.features, &info.features) {
+            return Err("missing required features".into());
+        }
+
+        let supports_present = |family: u32| {
+            self.surface
+                .as_ref()
+                .is_some_and(|v| v.supports_present(info.handle, family).unwrap_or(false))
+        };
+
+        QueueFamilies::select(
+            &info.queue_families,
+            self.surface.is_some(),
+            supports_present,
+        )
+        .ok_or_else(|| match self.surface {
+            Some(_) => "no queue family with graphics and present support".into(),
+            None => "no queue family with graphics support".into(),
+        })
+    }
+
+    pub fn build(self) -> Result<Arc<Device>> {
+        let devices = Self::physical_devices(&self.instance)?;
+
+        let mut rejected = vec![];
+        let mut best: Option<(u64, &PhysicalDeviceInfo, QueueFamilies)> = None;
+
+        for info in &devices {
+            if self.index.is_some_and(|v| v != info.index)
+                || self.filter.as_ref().is_some_and(|filter| !filter(info))
+            {
+                continue;
+            }
+
+            let families = match self.check(info) {
+                Ok(families) => families,
+                Err(reason) => {
+                    rejected.push(format!("{}: {reason}", info.name()));
+                    continue;
+                }
+            };
+
+            let score = score_physical_device(&info.properties);
+            if best.as_ref().is_none_or(|(best, ..)| score > *best) {
+                best = Some((score, info, families));
+            }
+        }
+
+        let Some((_, info, families)) = best else {
+            if let Some(index) = self.index.filter(|&v| v >= devices.len()) {
+                bail!(
+                    "there is no physical device {index}, there are only {}",
+                    devices.len()
+                );
+            }
+            bail!(
+                "no suitable physical device found, rejected: [{}]",
+                rejected.join(", ")
+            );
+        };
+
+        let mut extensions: Vec<*const c_char> =
+            self.extensions.iter().map(|v| v.as_ptr()).collect();
+        // has to be enabled if the device has it
+        if info.supports_extension(ash::khr::portability_subset::NAME) {
+            extensions.push(ash::khr::portability_subset::NAME.as_ptr());
+        }
+
+        Device::from_physical(
+            self.instance.clone(),
+            info,
+            families,
+            extensions,
+            self.features,
+        )
+    }
+}
@@ -0,0 +1,529 @@
+mod builder;
+mod queue;
+
+pub use builder::*;
+pub use queue::*;
+
+use anyhow::Result;
+use std::{
+    ffi::c_char,
+    sync::{Arc, Mutex},
+};
+
+use crate::prelude::{
+    non_coherent_range, Allocation, AllocationRequest, BufferAllocation, Instance,
+    MemoryAllocator, MemoryReport, TransferContext, UploadHandle,
+};
+use ash::vk;
+
+#[allow(unused)]
+pub struct Device {
+    handle: ash::Device,
+    physical_device: vk::PhysicalDevice,
+    instance: Arc<Instance>,
+    queue_family_index: u32,
+    queues: Queues,
+    queue_families: QueueFamilies,
+    properties: vk::PhysicalDeviceProperties,
+    features: vk::PhysicalDeviceFeatures,
+    allocator: MemoryAllocator,
+    memory_budget: bool,
+    descriptor_indexing: bool,
+    timeline_semaphores: bool,
+    dynamic_rendering: bool,
+    // created on the first staged upload
+    transfer: Mutex<Option<TransferContext>>,
+}
+
+#[allow(unused)]
+pub struct Queues {
+    graphics: Arc<Queue>,
+    // the queues are shared if they come from the same family
+    compute: Arc<Queue>,
+    transfer: Arc<Queue>,
+    present: Option<Arc<Queue>>,
+}
+
+impl Device {
+    /// picks the best physical device, see `DeviceBuilder` for more control
+    pub fn new(instance: Arc<Instance>) -> Result<Arc<Self>> {
+        DeviceBuilder::new(instance)
+            .extension(ash::khr::swapchain::NAME)
+            .build()
+    }
+
+    pub fn builder(instance: Arc<Instance>) -> DeviceBuilder {
+        DeviceBuilder::new(instance)
+    }
+
+    pub(crate) fn from_physical(
+        instance: Arc<Instance>,
+        info: &PhysicalDeviceInfo,
+        queue_families: QueueFamilies,
+        mut device_extension_names_raw: Vec<*const c_char>,
+        mut features: vk::PhysicalDeviceFeatures,
+    ) -> Result<Arc<Self>> {
+        let physical_device = info.handle;
+        let properties = info.properties;
+
+        // optional, samplers only use it when they ask for anisotropy
+        features.sampler_anisotropy |= info.features.sampler_anisotropy;
+
+        let memory_budget = info.supports_extension(ash::ext::memory_budget::NAME);
+        if memory_budget {
+            device_extension_names_raw.push(ash::ext::memory_budget::NAME.as_ptr());
+        }
+
+        let mut vulkan12_features = if properties.api_version >= vk::API_VERSION_1_2 {
+            vulkan12_features(&instance, physical_device)
+        } else {
+            vk::PhysicalDeviceVulkan12Features::default()
+        };
+        let descriptor_indexing = supports_bindless(&vulkan12_features);
+        let timeline_semaphores = vulkan12_features.timeline_semaphore == vk::TRUE;
+
+        // the core 1.3 entry points are used, so the extension alone isn't enough
+        let mut vulkan13_features = if properties.api_version >= vk::API_VERSION_1_3 {
+            vulkan13_features(&instance, physical_device)
+        } else {
+            vk::PhysicalDeviceVulkan13Features::default()
+        };
+        let dynamic_rendering = vulkan13_features.dynamic_rendering == vk::TRUE;
+
+        let priorities = [1.0];
+
+        let queue_infos: Vec<_> = queue_families
+            .unique()
+            .into_iter()
+            .map(|family| {
+                vk::DeviceQueueCreateInfo::default()
+                    .queue_family_index(family)
+                    .queue_priorities(&priorities)
+            })
+            .collect();
+
+        let device_create_info = vk::DeviceCreateInfo::default()
+            .queue_create_infos(&queue_infos)
+            .enabled_extension_names(&device_extension_names_raw)
+            .enabled_features(&features);
+        let device_create_info = if properties.api_version >= vk::API_VERSION_1_2 {
+            device_create_info.push_next(&mut vulkan12_features)
+        } else {
+            device_create_info
+        };
+        let device_create_info = if properties.api_version >= vk::API_VERSION_1_3 {
+            device_create_info.push_next(&mut vulkan13_features)
+        } else {
+            device_create_info
+        };
+
+        let device: ash::Device = unsafe {
+            instance
+                .as_raw()
+                .create_device(physical_device, &device_create_info, None)
+        }?;
+
+        let unique: Vec<_> = queue_families
+            .unique()
+            .into_iter()
+            .map(|family| {
+                let flags = info.queue_families[family as usize].queue_flags;
+                Arc::new(Queue::new(&device, family, flags))
+            })
+            .collect();
+        let queue = |family: u32| {
+            unique
+                .iter()
+                .find(|v| v.family_index() == family)
+                .unwrap()
+                .clone()
+        };
+        let queues = Queues {
+            graphics: queue(queue_families.graphics),
+            compute: queue(queue_families.compute),
+            transfer: queue(queue_families.transfer),
+            present: queue_families.present.map(queue),
+        };
+
+        let memory_properties = unsafe {
+            instance
+                .as_raw()
+                .get_physical_device_memory_properties(physical_device)
+        };
+
+        let allocator =
+            MemoryAllocator::new(memory_properties, properties.limits.buffer_image_granularity)
+                .with_non_coherent_atom_size(properties.limits.non_coherent_atom_size);
+
+        Ok(Self {
+            handle: device,
+            queue_family_index: queue_families.graphics,
+            instance,
+            queues,
+            queue_families,
+            physical_device,
+            properties,
+            features,
+            allocator,
+            memory_budget,
+            descriptor_indexing,
+            timeline_semaphores,
+            dynamic_rendering,
+            transfer: Mutex::default(),
+        }
+        .into())
+    }
+
+    pub fn as_raw(&self) -> &ash::Device {
+        &self.handle
+    }
+    pub fn as_raw_ref(&self) -> &ash::Device {
+        &self.handle
+    }
+    pub fn physical(&self) -> &vk::PhysicalDevice {
+        &self.physical_device
+    }
+    pub fn instance(&self) -> &Arc<Instance> {
+        &self.instance
+    }
+    /// the graphics queue family
+    pub fn queue_family_index(&self) -> u32 {
+        self.queue_family_index
+    }
+    pub fn physical_device_memory_properties(&self) -> vk::PhysicalDeviceMemoryProperties {
+        unsafe { self.instance.as_raw().get_physical_device_memory_properties(self.physical_device) }
+    }
+
+    pub fn properties(&self) -> &vk::PhysicalDeviceProperties {
+        &self.properties
+    }
+
+    /// the core features the device was created with
+    pub fn enabled_features(&self) -> &vk::PhysicalDeviceFeatures {
+        &self.features
+    }
+
+    /// true if the descriptor indexing features `BindlessHeap` needs are enabled
+    pub fn supports_descriptor_indexing(&self) -> bool {
+        self.descriptor_indexing
+    }
+
+    /// the descriptor limits, including the ones for update after bind sets
+    pub fn descriptor_indexing_properties(
+        &self,
+    ) -> vk::PhysicalDeviceDescriptorIndexingProperties<'static> {
+        let mut indexing = vk::PhysicalDeviceDescriptorIndexingProperties::default();
+        let mut properties = vk::PhysicalDeviceProperties2::default().push_next(&mut indexing);
+        unsafe {
+            self.instance
+                .as_raw()
+                .get_physical_device_properties2(self.physical_device, &mut properties)
+        };
+        indexing
+    }
+
+    /// true if `Semaphore::timeline` can be used
+    pub fn supports_timeline_semaphores(&self) -> bool {
+        self.timeline_semaphores
+    }
+
+    /// true if `CommandBuffer::begin_rendering` and `RenderingScope` can be used
+    pub fn supports_dynamic_rendering(&self) -> bool {
+        self.dynamic_rendering
+    }
+
+    pub fn allocator(&self) -> &MemoryAllocator {
+        &self.allocator
+    }
+
+    pub fn allocate_memory(&self, request: &AllocationRequest) -> Result<Allocation> {
+        self.allocator.allocate(&self.handle, request)
+    }
+
+    pub fn free_memory(&self, allocation: &Allocation) {
+        self.allocator.free(&self.handle, allocation)
+    }
+
+    fn mapped_range(
+        &self,
+        allocation: &Allocation,
+        offset: u64,
+        size: u64,
+    ) -> vk::MappedMemoryRange<'static> {
+        let (offset, size) = non_coherent_range(
+            allocation.offset() + offset,
+            size,
+            self.allocator.non_coherent_atom_size(),
+        );
+
+        vk::MappedMemoryRange::default()
+            .memory(allocation.memory())
+            .offset(offset)
+            .size(size)
+    }
+
+    /// makes host writes to `offset..offset + size` of the allocation visible to the device
+    /// does nothing for coherent memory
+    pub fn flush_memory(&self, allocation: &Allocation, offset: u64, size: u64) -> Result<()> {
+        if allocation.is_coherent() || allocation.mapped_ptr().is_none() {
+            return Ok(());
+        }
+
+        let range = self.mapped_range(allocation, offset, size);
+        unsafe { self.handle.flush_mapped_memory_ranges(&[range]) }?;
+        Ok(())
+    }
+
+    /// makes device writes to `offset..offset + size` of the allocation visible to the host
+    /// does nothing for coherent memory
+    pub fn invalidate_memory(&self, allocation: &Allocation, offset: u64, size: u64) -> Result<()> {
+        if allocation.is_coherent() || allocation.mapped_ptr().is_none() {
+            return Ok(());
+        }
+
+        let range = self.mapped_range(allocation, offset, size);
+        unsafe { self.handle.invalidate_mapped_memory_ranges(&[range]) }?;
+        Ok(())
+    }
+
+    pub fn format_properties(&self, format: vk::Format) -> vk::FormatProperties {
+        unsafe {
+            self.instance
+                .as_raw()
+                .get_physical_device_format_properties(self.physical_device, format)
+        }
+    }
+
+    /// true if optimal tiled images of `format` can be blitted with a linear filter
+    pub fn supports_linear_blit(&self, format: vk::Format) -> bool {
+        self.format_properties(format)
+            .optimal_tiling_features
+            .contains(
+                vk::FormatFeatureFlags::BLIT_SRC
+                    | vk::FormatFeatureFlags::BLIT_DST
+                    | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+            )
+    }
+
+    /// per heap memory usage of the allocator, with the driver budget if `VK_EXT_memory_budget` is supported
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = self.allocator.report();
+
+        if !self.memory_budget {
+            return report;
+        }
+
+        let mut budget = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let mut properties = vk::PhysicalDeviceMemoryProperties2::default().push_next(&mut budget);
+        unsafe {
+            self.instance
+                .as_raw()
+                .get_physical_device_memory_properties2(self.physical_device, &mut properties)
+        };
+
+        for heap in &mut report.heaps {
+            heap.budget = Some(budget.heap_budget[heap.heap_index as usize]);
+            heap.process_usage = Some(budget.heap_usage[heap.heap_index as usize]);
+        }
+
+        report
+    }
+
+    /// copies `data` through staging memory into the GPU and submits the commands recorded by `record`
+    /// `record` gets the staging buffer and the offset of the data inside of it
+    pub(crate) fn submit_transfer(
+        &self,
+        data: &[u8],
+        record: impl FnOnce(vk::CommandBuffer, vk::Buffer, u64),
+    ) -> Result<u64> {
+        self.with_transfer(|transfer| {
+            transfer.submit(&self.handle, &self.allocator, self.queue(), data, record)
+        })
+    }
+
+    /// submits the commands recorded by `record` and returns the `size` bytes they copied into the
+    /// readback buffer passed to `record`, blocks until the GPU is done
+    pub(crate) fn readback_transfer(
+        &self,
+        size: u64,
+        record: impl FnOnce(vk::CommandBuffer, vk::Buffer),
+    ) -> Result<Vec<u8>> {
+        self.with_transfer(|transfer| {
+            transfer.readback(&self.handle, &self.allocator, self.queue(), size, record)
+        })
+    }
+
+    fn with_transfer<R>(&self, f: impl FnOnce(&mut TransferContext) -> Result<R>) -> Result<R> {
+        let mut transfer = self.transfer.lock().unwrap();
+
+        let transfer = match transfer.as_mut() {
+            Some(transfer) => transfer,
+            None => transfer.insert(TransferContext::new(
+                &self.handle,
+                &self.allocator,
+                self.queue_family_index,
+            )?),
+        };
+
+        f(transfer)
+    }
+
+    /// copies `data` into `buffer` at `offset` using a staging buffer
+    /// the buffer needs `BufferUsageFlags::TRANSFER_DST`
+    pub fn upload_to_buffer(
+        self: &Arc<Self>,
+        buffer: &impl BufferAllocation,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<UploadHandle> {
+        if offset + data.len() as u64 > buffer.size() {
+            anyhow::bail!(
+                "upload of {} bytes at offset {offset} doesn't fit into a buffer of {} bytes",
+                data.len(),
+                buffer.size()
+            );
+        }
+
+        if data.is_empty() {
+            return Ok(UploadHandle::finished(self.clone()));
+        }
+
+        let dst = buffer.buffer();
+        let dst_offset = buffer.offset() + offset;
+
+        let id = self.submit_transfer(data, |command_buffer, src, src_offset| {
+            let region = vk::BufferCopy::default()
+                .src_offset(src_offset)
+                .dst_offset(dst_offset)
+                .size(data.len() as u64);
+            unsafe {
+                self.handle
+                    .cmd_copy_buffer(command_buffer, src, dst, &[region])
+            }
+        })?;
+
+        Ok(UploadHandle::new(self.clone(), id))
+    }
+
+    /// blocks until the transfer `id` finished
+    pub(crate) fn wait_transfer(&self, id: u64) -> Result<()> {
+        match self.transfer.lock().unwrap().as_mut() {
+            Some(transfer) => transfer.wait(&self.handle, &self.allocator, id),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn is_transfer_finished(&self, id: u64) -> Result<bool> {
+        match self.transfer.lock().unwrap().as_mut() {
+            Some(transfer) => transfer.is_finished(&self.handle, &self.allocator, id),
+            None => Ok(true),
+        }
+    }
+
+    /// the graphics queue
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queues.graphics
+    }
+
+    pub fn graphics_queue(&self) -> &Arc<Queue> {
+        &self.queues.graphics
+    }
+
+    /// the same as `graphics_queue` if there is no dedicated compute family
+    pub fn compute_queue(&self) -> &Arc<Queue> {
+        &self.queues.compute
+    }
+
+    /// a queue of a transfer-only family if there is one, `compute_queue` or `graphics_queue` otherwise
+    pub fn transfer_queue(&self) -> &Arc<Queue> {
+        &self.queues.transfer
+    }
+
+    /// `None` if the device wasn't built with `DeviceBuilder::surface`
+    pub fn present_queue(&self) -> Option<&Arc<Queue>> {
+        self.queues.present.as_ref()
+    }
+
+    pub fn queue_families(&self) -> &QueueFamilies {
+        &self.queue_families
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        unsafe {
+            self.handle.device_wait_idle().unwrap();
+            if let Some(transfer) = self.transfer.get_mut().unwrap().take() {
+                transfer.destroy(&self.handle, &self.allocator);
+            }
+            self.allocator.destroy(&self.handle);
+            self.handle.destroy_device(None);
+        }
+    }
+}
+
+/// the supported subset of the descriptor indexing and timeline semaphore features,
+/// everything else is off
+fn vulkan12_features(
+    instance: &Instance,
+    physical_device: vk::PhysicalDevice,
+) -> vk::PhysicalDeviceVulkan12Features<'static> {
+    let mut supported = vk::PhysicalDeviceVulkan12Features::default();
+    let mut features = vk::PhysicalDeviceFeatures2::default().push_next(&mut supported);
+    unsafe {
+        instance
+            .as_raw()
+            .get_physical_device_features2(physical_device, &mut features)
+    };
+
+    vk::PhysicalDeviceVulkan12Features {
+        descriptor_indexing: supported.descriptor_indexing,
+        shader_sampled_image_array_non_uniform_indexing: supported
+            .shader_sampled_image_array_non_uniform_indexing,
+        shader_storage_buffer_array_non_uniform_indexing: supported
+            .shader_storage_buffer_array_non_uniform_indexing,
+        descriptor_binding_sampled_image_update_after_bind: supported
+            .descriptor_binding_sampled_image_update_after_bind,
+        descriptor_binding_storage_buffer_update_after_bind: supported
+            .descriptor_binding_storage_buffer_update_after_bind,
+        descriptor_binding_update_unused_while_pending: supported
+            .descriptor_binding_update_unused_while_pending,
+        descriptor_binding_partially_bound: supported.descriptor_binding_partially_bound,
+        runtime_descriptor_array: supported.runtime_descriptor_array,
+        timeline_semaphore: supported.timeline_semaphore,
+        ..Default::default()
+    }
+}
+
+/// dynamic rendering if it is supported, everything else is off
+fn vulkan13_features(
+    instance: &Instance,
+    physical_device: vk::PhysicalDevice,
+) -> vk::PhysicalDeviceVulkan13Features<'static> {
+    let mut supported = vk::PhysicalDeviceVulkan13Features::default();
+    let mut features = vk::PhysicalDeviceFeatures2::default().push_next(&mut supported);
+    unsafe {
+        instance
+            .as_raw()
+            .get_physical_device_features2(physical_device, &mut features)
+    };
+
+    vk::PhysicalDeviceVulkan13Features {
+        dynamic_rendering: supported.dynamic_rendering,
+        ..Default::default()
+    }
+}
+
+fn supports_bindless(features: &vk::PhysicalDeviceVulkan12Features) -> bool {
+    [
+        features.shader_sampled_image_array_non_uniform_indexing,
+        features.shader_storage_buffer_array_non_uniform_indexing,
+        features.descriptor_binding_sampled_image_update_after_bind,
+        features.descriptor_binding_storage_buffer_update_after_bind,
+        features.descriptor_binding_update_unused_while_pending,
+        features.descriptor_binding_partially_bound,
+        features.runtime_descriptor_array,
+    ]
+    .iter()
+    .all(|&v| v == vk::TRUE)
+}
@@ -0,0 +1,63 @@
+use std::sync::Mutex;
+
+use anyhow::Result;
+use ash::vk;
+
+/// a device queue, access to the raw queue goes through a lock
+/// because vulkan doesn't allow submitting to the same queue from two threads at once
+pub struct Queue {
+    handle: vk::Queue,
+    // the device is only needed for its function pointers, the `Device` owns the queue
+    device: ash::Device,
+    family_index: u32,
+    flags: vk::QueueFlags,
+    lock: Mutex<()>,
+}
+
+impl Queue {
+    pub(crate) fn new(device: &ash::Device, family_index: u32, flags: vk::QueueFlags) -> Self {
+        let handle = unsafe { device.get_device_queue(family_index, 0) };
+
+        Self {
+            handle,
+            device: device.clone(),
+            family_index,
+            flags,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// runs `f` while holding the submit lock of the queue
+    pub fn with_lock<R>(&self, f: impl FnOnce(vk::Queue) -> R) -> R {
+        let _lock = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+        f(self.handle)
+    }
+
+    pub fn submit(&self, submits: &[vk::SubmitInfo], fence: vk::Fence) -> Result<()> {
+        self.with_lock(|queue| unsafe { self.device.queue_submit(queue, submits, fence) })?;
+        Ok(())
+    }
+
+    pub fn wait_idle(&self) -> Result<()> {
+        self.with_lock(|queue| unsafe { self.device.queue_wait_idle(queue) })?;
+        Ok(())
+    }
+
+    /// the capabilities of the queue family
+    pub fn flags(&self) -> vk::QueueFlags {
+        self.flags
+    }
+
+    pub fn supports(&self, flags: vk::QueueFlags) -> bool {
+        self.flags.contains(flags)
+    }
+
+    pub fn family_index(&self) -> u32 {
+        self.family_index
+    }
+
+    /// the queue must not be used without `with_lock`
+    pub fn as_raw(&self) -> &vk::Queue {
+        &self.handle
+    }
+}
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::prelude::{
+    record_transition, CommandBuffer, CommandPool, Device, Fence, Framebuffer, ImageView, Queue,
+    RenderTarget, Semaphore, Submission,
+};
+
+/// the objects one frame in flight needs for itself
+struct FrameSlot {
+    pool: Arc<CommandPool>,
+    fence: Arc<Fence>,
+    image_available: Arc<Semaphore>,
+}
+
+/// the frame that is being recorded, it has to be handed back to `FrameContext::end_frame`
+pub struct Frame {
+    /// already recording, the image is in `COLOR_ATTACHMENT_OPTIMAL`
+    pub command_buffer: CommandBuffer,
+    pub image_index: u32,
+    pub image: vk::Image,
+    pub view: Arc<ImageView>,
+    /// only there if a render pass was set on the target
+    pub framebuffer: Option<Arc<Framebuffer>>,
+    pub extent: vk::Extent2D,
+    pub format: vk::Format,
+    /// which of the frames in flight this is
+    pub frame_index: usize,
+}
+
+/// runs the acquire, record, submit and present loop for up to `frames_in_flight` frames at once
+pub struct FrameContext {
+    device: Arc<Device>,
+    target: Arc<dyn RenderTarget>,
+    frames: Vec<FrameSlot>,
+    // one per image of the target, presenting holds on to it until the image is acquired again
+    render_finished: Vec<Arc<Semaphore>>,
+    current: usize,
+    // set on resize or when acquire or present reported a stale swapchain
+    needs_recreation: bool,
+}
+
+fn image_semaphores(
+    device: &Arc<Device>,
+    target: &dyn RenderTarget,
+) -> Result<Vec<Arc<Semaphore>>> {
+    target
+        .images()
+        .iter()
+        .map(|_| Semaphore::binary(device.clone()))
+        .collect()
+}
+
+fn color_range() -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .level_count(1)
+        .layer_count(1)
+}
+
+impl FrameContext {
+    pub fn new(
+        device: Arc<Device>,
+        target: Arc<dyn RenderTarget>,
+        frames_in_flight: usize,
+    ) -> Result<Self> {
+        let frames = (0..frames_in_flight.max(1))
+            .map(|_| {
+                Ok(FrameSlot {
+                    pool: CommandPool::new(device.clone())?,
+                    fence: Fence::new(device.clone())?,
+                    image_available: Semaphore::binary(device.clone())?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            render_finished: image_semaphores(&device, &*target)?,
+            device,
+            target,
+            frames,
+            current: 0,
+            needs_recreation: false,
+        })
+    }
+
+    /// waits until the frame slot is free again, acquires an image of the target
+    /// and starts recording a command buffer for it
+    /// returns `None` if the frame has to be skipped, because the window is minimized
+    /// or the target went out of date
+    pub fn begin_frame(&mut self) -> Result<Option<Frame>> {
+        if self.needs_recreation && !self.recreate_swapchain()? {
+            return Ok(None);
+        }
+
+        let slot = &self.frames[self.current];
+
+        // releases the command buffer of the last frame that used the slot
+        slot.fence.wait_for_finished()?;
+
+        let Some((image_index, status)) = self.target.acquire_image(&slot.image_available)?
+        else {
+            self.needs_recreation = true;
+            return Ok(None);
+        };
+        // the image is acquired already, so it gets rendered and presented before recreating
+        self.needs_recreation |= status.needs_recreation();
+        let image = self.target.images()[image_index as usize];
+
+        let command_buffer = CommandBuffer::new(slot.pool.clone(), self.device.clone())?;
+        command_buffer.begin();
+
+        // starts at the stage that waits for `image_available`,
+        // so the transition can't happen before the image was acquired
+        let barrier = vk::ImageMemoryBarrier::default()
+            .image(image)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .subresource_range(color_range());
+        unsafe {
+            self.device.as_raw().cmd_pipeline_barrier(
+                *command_buffer.as_raw(),
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            )
+        };
+
+        Ok(Some(Frame {
+            command_buffer,
+            image_index,
+            image,
+            view: self.target.image_views()[image_index as usize].clone(),
+            framebuffer: self.target.framebuffer(image_index),
+            extent: self.target.extent(),
+            format: self.target.format(),
+            frame_index: self.current,
+        }))
+    }
+
+    /// submits the frame to the graphics queue and presents it
+    pub fn end_frame(&mut self, frame: Frame) -> Result<()> {
+        let slot = &self.frames[frame.frame_index];
+        let render_finished = &self.render_finished[frame.image_index as usize];
+
+        record_transition(
+            self.device.as_raw(),
+            *frame.command_buffer.as_raw(),
+            frame.image,
+            color_range(),
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            self.target.present_layout(),
+        );
+        frame.command_buffer.end();
+
+        Submission::new(vec![frame.command_buffer])
+            .keep_alive(frame.framebuffer)
+            .wait(
+                &slot.image_available,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            )
+            .signal(render_finished)
+            .submit(self.device.graphics_queue(), Some(&slot.fence))?;
+
+        let status = self.target.present_image(
+            frame.image_index,
+            self.present_queue(),
+            render_finished,
+        )?;
+        self.needs_recreation |= status.needs_recreation();
+
+        self.current = (frame.frame_index + 1) % self.frames.len();
+        Ok(())
+    }
+
+    /// the swapchain gets recreated before the next frame, call it when the window was resized
+    pub fn resize(&mut self) {
+        self.needs_recreation = true;
+    }
+
+    /// returns false if the window has no size, the old swapchain is kept until it has one
+    fn recreate_swapchain(&mut self) -> Result<bool> {
+        // the old swapchain can only go away once no frame uses its images
+        self.wait_idle()?;
+        self.present_queue().wait_idle()?;
+
+        let Some(target) = self.target.clone().recreate_target()? else {
+            return Ok(false);
+        };
+        // nothing waits on the old semaphores anymore, the new target can have more images
+        self.render_finished = image_semaphores(&self.device, &*target)?;
+        self.target = target;
+        self.needs_recreation = false;
+        Ok(true)
+    }
+
+    fn present_queue(&self) -> &Queue {
+        self.device
+            .present_queue()
+            .unwrap_or(self.device.graphics_queue())
+    }
+
+    /// blocks until all frames in flight finished
+    pub fn wait_idle(&self) -> Result<()> {
+        for slot in &self.frames {
+            slot.fence.wait_for_finished()?;
+        }
+        Ok(())
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// changes after a recreation, compare `RenderTarget::generation` to notice it
+    pub fn target(&self) -> &Arc<dyn RenderTarget> {
+        &self.target
+    }
+}
+
+impl Drop for FrameContext {
+    fn drop(&mut self) {
+        // the semaphores must not be destroyed while the GPU still uses them
+        self.wait_idle().ok();
+    }
+}
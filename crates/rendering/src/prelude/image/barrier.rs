@@ -0,0 +1,166 @@
+use ash::vk;
+
+/// the stages and accesses that have to finish before an image leaves `layout`,
+/// or that have to wait for an image entering it
+pub fn layout_access(layout: vk::ImageLayout) -> (vk::PipelineStageFlags, vk::AccessFlags) {
+    use vk::{AccessFlags as A, ImageLayout as L, PipelineStageFlags as S};
+
+    match layout {
+        L::UNDEFINED | L::PREINITIALIZED => (S::TOP_OF_PIPE, A::empty()),
+        L::TRANSFER_DST_OPTIMAL => (S::TRANSFER, A::TRANSFER_WRITE),
+        L::TRANSFER_SRC_OPTIMAL => (S::TRANSFER, A::TRANSFER_READ),
+        L::SHADER_READ_ONLY_OPTIMAL => (
+            S::VERTEX_SHADER | S::FRAGMENT_SHADER | S::COMPUTE_SHADER,
+            A::SHADER_READ,
+        ),
+        L::COLOR_ATTACHMENT_OPTIMAL => (
+            S::COLOR_ATTACHMENT_OUTPUT,
+            A::COLOR_ATTACHMENT_READ | A::COLOR_ATTACHMENT_WRITE,
+        ),
+        L::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+        | L::DEPTH_ATTACHMENT_OPTIMAL
+        | L::STENCIL_ATTACHMENT_OPTIMAL => (
+            S::EARLY_FRAGMENT_TESTS | S::LATE_FRAGMENT_TESTS,
+            A::DEPTH_STENCIL_ATTACHMENT_READ | A::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        ),
+        L::DEPTH_STENCIL_READ_ONLY_OPTIMAL | L::DEPTH_READ_ONLY_OPTIMAL => (
+            S::EARLY_FRAGMENT_TESTS | S::LATE_FRAGMENT_TESTS | S::FRAGMENT_SHADER,
+            A::DEPTH_STENCIL_ATTACHMENT_READ | A::SHADER_READ,
+        ),
+        // presenting waits on a semaphore, the barrier doesn't need to cover it
+        L::PRESENT_SRC_KHR => (S::BOTTOM_OF_PIPE, A::empty()),
+        _ => (S::ALL_COMMANDS, A::MEMORY_READ | A::MEMORY_WRITE),
+    }
+}
+
+pub fn format_aspect(format: vk::Format) -> vk::ImageAspectFlags {
+    match format {
+        vk::Format::D16_UNORM | vk::Format::D32_SFLOAT | vk::Format::X8_D24_UNORM_PACK32 => {
+            vk::ImageAspectFlags::DEPTH
+        }
+        vk::Format::S8_UINT => vk::ImageAspectFlags::STENCIL,
+        vk::Format::D16_UNORM_S8_UINT
+        | vk::Format::D24_UNORM_S8_UINT
+        | vk::Format::D32_SFLOAT_S8_UINT => {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        }
+        _ => vk::ImageAspectFlags::COLOR,
+    }
+}
+
+/// number of mip levels down to 1x1
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+pub(crate) fn record_transition(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    range: vk::ImageSubresourceRange,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+) {
+    let (src_stage, src_access) = layout_access(old_layout);
+    let (dst_stage, dst_access) = layout_access(new_layout);
+
+    let barrier = vk::ImageMemoryBarrier::default()
+        .image(image)
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_access_mask(src_access)
+        .dst_access_mask(dst_access)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .subresource_range(range);
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            src_stage,
+            dst_stage,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier],
+        )
+    };
+}
+
+/// fills mip levels 1.. by blitting every level into the next one
+/// every level has to be in `TRANSFER_DST_OPTIMAL`, they all end up in `final_layout`
+pub(crate) fn record_mipmaps(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    extent: vk::Extent3D,
+    mip_levels: u32,
+    layers: u32,
+    final_layout: vk::ImageLayout,
+) {
+    let level = |level: u32| {
+        vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(level)
+            .level_count(1)
+            .layer_count(layers)
+    };
+    let layers_of = |level: u32| {
+        vk::ImageSubresourceLayers::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .mip_level(level)
+            .layer_count(layers)
+    };
+    let size = |level: u32| vk::Offset3D {
+        x: (extent.width >> level).max(1) as i32,
+        y: (extent.height >> level).max(1) as i32,
+        z: 1,
+    };
+
+    for i in 1..mip_levels {
+        record_transition(
+            device,
+            command_buffer,
+            image,
+            level(i - 1),
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        );
+
+        let blit = vk::ImageBlit::default()
+            .src_subresource(layers_of(i - 1))
+            .src_offsets([vk::Offset3D::default(), size(i - 1)])
+            .dst_subresource(layers_of(i))
+            .dst_offsets([vk::Offset3D::default(), size(i)]);
+
+        unsafe {
+            device.cmd_blit_image(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[blit],
+                vk::Filter::LINEAR,
+            )
+        };
+
+        record_transition(
+            device,
+            command_buffer,
+            image,
+            level(i - 1),
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            final_layout,
+        );
+    }
+
+    record_transition(
+        device,
+        command_buffer,
+        image,
+        level(mip_levels - 1),
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        final_layout,
+    );
+}
@@ -0,0 +1,296 @@
+mod barrier;
+
+pub use barrier::*;
+
+use crate::prelude::{
+    format_texel_size, Allocation, AllocationKind, AllocationRequest, Device, MemoryCategory,
+};
+use anyhow::{bail, Context, Result};
+use ash::vk;
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+};
+
+pub use vk::{ImageCreateInfo, ImageViewCreateInfo};
+
+
+#[allow(unused)]
+pub struct Image {
+    handle: vk::Image,
+    device: Arc<Device>,
+    info: ImageCreateInfo<'static>,
+    allocation: Allocation,
+    // the layout after all recorded commands ran
+    layout: Mutex<vk::ImageLayout>,
+}
+
+impl Image {
+    pub fn new(device: Arc<Device>, info: ImageCreateInfo<'static>) -> Result<Arc<Self>> {
+        let handle = unsafe { device.as_raw().create_image(&info, None) }?;
+
+        let requirements = unsafe { device.as_raw().get_image_memory_requirements(handle) };
+
+        let kind = match info.tiling {
+            vk::ImageTiling::LINEAR => AllocationKind::Linear,
+            _ => AllocationKind::Optimal,
+        };
+
+        let allocation = device
+            .allocate_memory(&AllocationRequest {
+                requirements,
+                visibility: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                kind,
+                dedicated: false,
+                category: MemoryCategory::Image,
+            })
+            .inspect_err(|_| unsafe { device.as_raw().destroy_image(handle, None) })?;
+
+        let bind = unsafe {
+            device
+                .as_raw()
+                .bind_image_memory(handle, allocation.memory(), allocation.offset())
+        };
+        if let Err(err) = bind {
+            unsafe { device.as_raw().destroy_image(handle, None) };
+            device.free_memory(&allocation);
+            return Err(err.into());
+        }
+
+        Ok(Self {
+            device,
+            handle,
+            layout: Mutex::new(info.initial_layout),
+            info,
+            allocation,
+        }
+        .into())
+    }
+
+    /// a sampled 2D image in `R8G8B8A8_SRGB`, see `from_bytes`
+    pub fn from_rgba8(
+        device: Arc<Device>,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        generate_mips: bool,
+    ) -> Result<Arc<Self>> {
+        Self::from_bytes(
+            device,
+            vk::Format::R8G8B8A8_SRGB,
+            vk::Extent2D { width, height },
+            pixels,
+            generate_mips,
+        )
+    }
+
+    /// creates a sampled 2D image and uploads `data` through a staging buffer
+    /// this blocks until the upload finished, the image ends up in `SHADER_READ_ONLY_OPTIMAL`
+    /// mips are only generated if the format supports linear blits, otherwise there is one level
+    pub fn from_bytes(
+        device: Arc<Device>,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        data: &[u8],
+        generate_mips: bool,
+    ) -> Result<Arc<Self>> {
+        let texel_size =
+            format_texel_size(format).with_context(|| format!("can't upload {format:?}"))?;
+
+        let expected = extent.width as u64 * extent.height as u64 * texel_size;
+        if data.len() as u64 != expected {
+            bail!(
+                "{}x{} {format:?} needs {expected} bytes but got {}",
+                extent.width,
+                extent.height,
+                data.len()
+            );
+        }
+
+        let mip_levels = if generate_mips && device.supports_linear_blit(format) {
+            mip_level_count(extent.width, extent.height)
+        } else {
+            1
+        };
+
+        let info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(format)
+            .extent(extent.into())
+            .mip_levels(mip_levels)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(
+                vk::ImageUsageFlags::SAMPLED
+                    | vk::ImageUsageFlags::TRANSFER_DST
+                    | vk::ImageUsageFlags::TRANSFER_SRC,
+            )
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+
+        let image = Self::new(device.clone(), info)?;
+        let handle = image.handle;
+        let range = image.subresource_range();
+        let final_layout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+
+        let id = device.submit_transfer(data, |command_buffer, src, offset| {
+            let device = device.as_raw();
+            let dst_layout = vk::ImageLayout::TRANSFER_DST_OPTIMAL;
+
+            record_transition(
+                device,
+                command_buffer,
+                handle,
+                range,
+                vk::ImageLayout::UNDEFINED,
+                dst_layout,
+            );
+
+            let region = vk::BufferImageCopy::default()
+                .buffer_offset(offset)
+                .image_subresource(
+                    vk::ImageSubresourceLayers::default()
+                        .aspect_mask(range.aspect_mask)
+                        .layer_count(1),
+                )
+                .image_extent(info.extent);
+            unsafe {
+                device.cmd_copy_buffer_to_image(command_buffer, src, handle, dst_layout, &[region])
+            };
+
+            if mip_levels > 1 {
+                record_mipmaps(
+                    device,
+                    command_buffer,
+                    handle,
+                    info.extent,
+                    mip_levels,
+                    1,
+                    final_layout,
+                );
+            } else {
+                record_transition(device, command_buffer, handle, range, dst_layout, final_layout);
+            }
+        })?;
+        device.wait_transfer(id)?;
+
+        image.set_layout(final_layout);
+        Ok(image)
+    }
+
+    /// every mip level and layer of the image
+    pub fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange::default()
+            .aspect_mask(format_aspect(self.info.format))
+            .level_count(self.info.mip_levels)
+            .layer_count(self.info.array_layers)
+    }
+
+    /// the layout the image is in once all recorded commands finished
+    pub fn layout(&self) -> vk::ImageLayout {
+        *self.layout.lock().unwrap()
+    }
+
+    pub(crate) fn set_layout(&self, layout: vk::ImageLayout) {
+        *self.layout.lock().unwrap() = layout;
+    }
+
+    pub fn mip_levels(&self) -> u32 {
+        self.info.mip_levels
+    }
+
+    pub fn usage(&self) -> vk::ImageUsageFlags {
+        self.info.usage
+    }
+
+    pub fn as_raw(&self) -> &vk::Image {
+        &self.handle
+    }
+
+    pub fn format(&self) -> vk::Format {
+        self.info.format
+    }
+
+    pub fn extent(&self) -> vk::Extent3D {
+        self.info.extent
+    }
+
+    pub fn allocation(&self) -> &Allocation {
+        &self.allocation
+    }
+}
+
+#[allow(unused)]
+pub struct ImageView {
+    handle: vk::ImageView,
+    info: ImageViewCreateInfo<'static>,
+    device: Arc<Device>,
+    usage: vk::ImageUsageFlags,
+    // we need to store the image here just to ensure that its not being droped
+    image: Option<Arc<Image>>,
+    // keeps images the crate doesn't own alive, like the ones of a swapchain
+    owner: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl ImageView {
+
+    pub fn new(device: Arc<Device>, image: Arc<Image>, info: ImageViewCreateInfo<'static>) -> Result<Arc<Self>> {
+        let handle = unsafe { device.as_raw().create_image_view(&info, None) }?;
+
+        Ok( Self { handle, info, device, usage: image.usage(), image: Some(image), owner: None }.into() )
+    }
+
+    /// a view of an image that isn't an `Image`, `owner` has to keep it alive
+    pub(crate) fn for_image_of(
+        device: Arc<Device>,
+        owner: Arc<dyn Any + Send + Sync>,
+        usage: vk::ImageUsageFlags,
+        info: ImageViewCreateInfo<'static>,
+    ) -> Result<Arc<Self>> {
+        let handle = unsafe { device.as_raw().create_image_view(&info, None) }?;
+
+        Ok(Self {
+            handle,
+            info,
+            device,
+            usage,
+            image: None,
+            owner: Some(owner),
+        }
+        .into())
+    }
+
+    pub fn as_raw(&self) -> &vk::ImageView {
+        &self.handle
+    }
+
+    /// `None` for views of swapchain images
+    pub fn image(&self) -> Option<&Arc<Image>> {
+        self.image.as_ref()
+    }
+
+    /// the usage of the image the view was created for
+    pub fn usage(&self) -> vk::ImageUsageFlags {
+        self.usage
+    }
+}
+
+
+
+
+
+
+
+impl Drop for ImageView {
+    fn drop(&mut self) {
+        unsafe { self.device.as_raw().destroy_image_view(self.handle, None) };
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        unsafe { self.device.as_raw().destroy_image(self.handle, None) };
+        self.device.free_memory(&self.allocation);
+    }
+}
@@ -0,0 +1,377 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use anyhow::{Context, Result};
+use ash::vk;
+
+use super::{
+    align_up, find_memorytype_index, Allocation, AllocationRequest, AllocationSource, CategoryUsage,
+    FreeList, HeapReport, MappedPtr, MemoryBackend, MemoryCategory, MemoryReport,
+};
+
+pub const DEFAULT_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+
+struct MemoryBlock {
+    memory: vk::DeviceMemory,
+    mapped: Option<MappedPtr>,
+    free_list: FreeList,
+}
+
+#[derive(Default)]
+struct HeapStats {
+    dedicated_bytes: u64,
+    dedicated_count: usize,
+    categories: BTreeMap<MemoryCategory, CategoryUsage>,
+}
+
+struct State {
+    // indexed by memory type, freed blocks leave a `None` behind so block indices stay stable
+    blocks: Vec<Vec<Option<MemoryBlock>>>,
+    // indexed by heap
+    heaps: Vec<HeapStats>,
+}
+
+impl State {
+    fn heap(
+        &mut self,
+        properties: &vk::PhysicalDeviceMemoryProperties,
+        memory_type_index: u32,
+    ) -> &mut HeapStats {
+        let heap_index = properties.memory_types[memory_type_index as usize].heap_index;
+        &mut self.heaps[heap_index as usize]
+    }
+}
+
+/// sub-allocates resources out of large `vk::DeviceMemory` blocks, one list of blocks per memory type
+/// resources bigger than half a block get a dedicated allocation
+pub struct MemoryAllocator {
+    properties: vk::PhysicalDeviceMemoryProperties,
+    granularity: u64,
+    non_coherent_atom_size: u64,
+    block_size: u64,
+    state: Mutex<State>,
+}
+
+impl MemoryAllocator {
+    pub fn new(
+        properties: vk::PhysicalDeviceMemoryProperties,
+        buffer_image_granularity: u64,
+    ) -> Self {
+        Self {
+            properties,
+            granularity: buffer_image_granularity,
+            non_coherent_atom_size: 1,
+            block_size: DEFAULT_BLOCK_SIZE,
+            state: Mutex::new(State {
+                blocks: (0..properties.memory_type_count).map(|_| vec![]).collect(),
+                heaps: (0..properties.memory_heap_count)
+                    .map(|_| HeapStats::default())
+                    .collect(),
+            }),
+        }
+    }
+
+    pub fn with_block_size(mut self, block_size: u64) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// allocations in host visible but not coherent memory are padded to `nonCoherentAtomSize`,
+    /// so they can be flushed and invalidated without touching their neighbours
+    pub fn with_non_coherent_atom_size(mut self, atom_size: u64) -> Self {
+        self.non_coherent_atom_size = atom_size.max(1);
+        self
+    }
+
+    pub fn non_coherent_atom_size(&self) -> u64 {
+        self.non_coherent_atom_size
+    }
+
+    pub fn properties(&self) -> &vk::PhysicalDeviceMemoryProperties {
+        &self.properties
+    }
+
+    /// small heaps (e.g. the 256MB BAR heap) get smaller blocks so they don't run out
+    pub fn block_size(&self, memory_type_index: u32) -> u64 {
+        let heap_index = self.properties.memory_types[memory_type_index as usize].heap_index;
+        let heap_size = self.properties.memory_heaps[heap_index as usize].size;
+
+        self.block_size.min(heap_size / 8).max(1)
+    }
+
+    fn is_host_visible(&self, memory_type_index: u32) -> bool {
+        self.properties.memory_types[memory_type_index as usize]
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+    }
+
+    fn is_coherent(&self, memory_type_index: u32) -> bool {
+        self.properties.memory_types[memory_type_index as usize]
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_COHERENT)
+    }
+
+    fn allocate_raw(
+        &self,
+        backend: &impl MemoryBackend,
+        memory_type_index: u32,
+        size: u64,
+    ) -> Result<(vk::DeviceMemory, Option<MappedPtr>)> {
+        let memory = backend
+            .allocate_memory(memory_type_index, size)
+            .with_context(|| {
+                format!("failed to allocate {size} bytes of memory type {memory_type_index}")
+            })?;
+
+        if !self.is_host_visible(memory_type_index) {
+            return Ok((memory, None));
+        }
+
+        match backend.map_memory(memory) {
+            Ok(ptr) => Ok((memory, Some(MappedPtr(ptr)))),
+            Err(err) => {
+                backend.free_memory(memory, false);
+                Err(err).context("failed to map host visible memory")
+            }
+        }
+    }
+
+    pub fn allocate(
+        &self,
+        backend: &impl MemoryBackend,
+        request: &AllocationRequest,
+    ) -> Result<Allocation> {
+        let mut requirements = request.requirements;
+
+        let memory_type_index =
+            find_memorytype_index(&requirements, &self.properties, request.visibility)
+                .with_context(|| format!("no memory type supports {:?}", request.visibility))?;
+
+        let coherent = self.is_coherent(memory_type_index);
+        if self.is_host_visible(memory_type_index) && !coherent {
+            let atom_size = self.non_coherent_atom_size;
+            requirements.size = align_up(requirements.size, atom_size);
+            requirements.alignment = requirements.alignment.max(atom_size);
+        }
+
+        let block_size = self.block_size(memory_type_index);
+
+        if request.dedicated || requirements.size > block_size / 2 {
+            let (memory, mapped) =
+                self.allocate_raw(backend, memory_type_index, requirements.size)?;
+
+            let allocation = Allocation {
+                memory,
+                offset: 0,
+                size: requirements.size,
+                memory_type_index,
+                mapped,
+                coherent,
+                source: AllocationSource::Dedicated,
+                category: request.category,
+            };
+
+            let mut state = self.state.lock().unwrap();
+            let heap = state.heap(&self.properties, memory_type_index);
+            heap.dedicated_bytes += allocation.size;
+            heap.dedicated_count += 1;
+            heap.categories
+                .entry(allocation.category)
+                .or_default()
+                .add(allocation.size);
+
+            return Ok(allocation);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let allocation = self.allocate_in_blocks(
+            backend,
+            &mut state.blocks[memory_type_index as usize],
+            request,
+            requirements,
+            memory_type_index,
+        )?;
+
+        state
+            .heap(&self.properties, memory_type_index)
+            .categories
+            .entry(allocation.category)
+            .or_default()
+            .add(allocation.size);
+
+        Ok(allocation)
+    }
+
+    fn allocate_in_blocks(
+        &self,
+        backend: &impl MemoryBackend,
+        blocks: &mut Vec<Option<MemoryBlock>>,
+        request: &AllocationRequest,
+        requirements: vk::MemoryRequirements,
+        memory_type_index: u32,
+    ) -> Result<Allocation> {
+        let coherent = self.is_coherent(memory_type_index);
+
+        let allocate_in = |index: usize, block: &mut MemoryBlock| {
+            let offset = block.free_list.allocate(
+                requirements.size,
+                requirements.alignment,
+                request.kind,
+            )?;
+
+            Some(Allocation {
+                memory: block.memory,
+                offset,
+                size: requirements.size,
+                memory_type_index,
+                mapped: block
+                    .mapped
+                    .map(|v| MappedPtr(unsafe { v.0.add(offset as usize) })),
+                coherent,
+                source: AllocationSource::Block(index),
+                category: request.category,
+            })
+        };
+
+        for (index, block) in blocks.iter_mut().enumerate() {
+            if let Some(allocation) = block.as_mut().and_then(|v| allocate_in(index, v)) {
+                return Ok(allocation);
+            }
+        }
+
+        let block_size = self.block_size(memory_type_index);
+        let (memory, mapped) = self.allocate_raw(backend, memory_type_index, block_size)?;
+
+        let mut block = MemoryBlock {
+            memory,
+            mapped,
+            free_list: FreeList::new(block_size, self.granularity),
+        };
+
+        let index = blocks
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or(blocks.len());
+        let Some(allocation) = allocate_in(index, &mut block) else {
+            backend.free_memory(block.memory, block.mapped.is_some());
+            anyhow::bail!("allocation doesn't fit into a new memory block");
+        };
+
+        if index == blocks.len() {
+            blocks.push(Some(block));
+        } else {
+            blocks[index] = Some(block);
+        }
+
+        Ok(allocation)
+    }
+
+    pub fn free(&self, backend: &impl MemoryBackend, allocation: &Allocation) {
+        let mut state = self.state.lock().unwrap();
+
+        let heap = state.heap(&self.properties, allocation.memory_type_index);
+        if let Some(usage) = heap.categories.get_mut(&allocation.category) {
+            usage.remove(allocation.size);
+        }
+
+        let AllocationSource::Block(index) = allocation.source else {
+            heap.dedicated_bytes -= allocation.size;
+            heap.dedicated_count -= 1;
+            backend.free_memory(allocation.memory, allocation.mapped.is_some());
+            return;
+        };
+
+        let blocks = &mut state.blocks[allocation.memory_type_index as usize];
+
+        let Some(block) = blocks[index].as_mut() else {
+            return;
+        };
+
+        let freed = block.free_list.free(allocation.offset);
+        debug_assert!(freed, "allocation was freed twice");
+
+        // keep one empty block around so allocating and freeing in a loop doesn't hit the driver
+        let live_blocks = blocks.iter().filter(|v| v.is_some()).count();
+        if blocks[index]
+            .as_ref()
+            .is_some_and(|v| v.free_list.is_empty())
+            && live_blocks > 1
+        {
+            let block = blocks[index].take().unwrap();
+            backend.free_memory(block.memory, block.mapped.is_some());
+        }
+    }
+
+    /// usage of every heap as seen by the allocator, `budget` and `process_usage` are left empty
+    pub fn report(&self) -> MemoryReport {
+        let state = self.state.lock().unwrap();
+
+        let heaps = state
+            .heaps
+            .iter()
+            .enumerate()
+            .map(|(heap_index, stats)| {
+                let heap = self.properties.memory_heaps[heap_index];
+                let mut report = HeapReport {
+                    heap_index: heap_index as u32,
+                    flags: heap.flags,
+                    size: heap.size,
+                    allocated: stats.dedicated_bytes,
+                    used: stats.dedicated_bytes,
+                    dedicated_count: stats.dedicated_count,
+                    allocation_count: stats.dedicated_count,
+                    categories: stats.categories.clone(),
+                    ..Default::default()
+                };
+
+                let blocks = state
+                    .blocks
+                    .iter()
+                    .enumerate()
+                    .filter(|(memory_type_index, _)| {
+                        self.properties.memory_types[*memory_type_index].heap_index
+                            == heap_index as u32
+                    })
+                    .flat_map(|(_, blocks)| blocks.iter().flatten());
+
+                let (mut free, mut largest_free) = (0, 0);
+                for block in blocks {
+                    let list = &block.free_list;
+                    report.allocated += list.size();
+                    report.used += list.used();
+                    report.block_count += 1;
+                    report.allocation_count += list.allocation_count();
+                    free += list.size() - list.used();
+                    largest_free = largest_free.max(list.largest_free_range());
+                }
+
+                if free > 0 {
+                    report.fragmentation = 1.0 - largest_free as f32 / free as f32;
+                }
+                report
+            })
+            .collect();
+
+        MemoryReport { heaps }
+    }
+
+    /// number of `vk::DeviceMemory` blocks, dedicated allocations aren't counted
+    pub fn block_count(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .blocks
+            .iter()
+            .flatten()
+            .filter(|v| v.is_some())
+            .count()
+    }
+
+    /// frees all blocks, every allocation has to be freed before this
+    pub fn destroy(&self, backend: &impl MemoryBackend) {
+        for block in self.state.lock().unwrap().blocks.iter_mut().flatten() {
+            if let Some(block) = block.take() {
+                backend.free_memory(block.memory, block.mapped.is_some());
+            }
+        }
+    }
+}
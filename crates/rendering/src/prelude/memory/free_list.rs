@@ -0,0 +1,171 @@
+use super::AllocationKind;
+
+#[derive(Debug, Clone, Copy)]
+struct Chunk {
+    offset: u64,
+    size: u64,
+    /// `None` if the chunk is free
+    kind: Option<AllocationKind>,
+}
+
+impl Chunk {
+    fn end(&self) -> u64 {
+        self.offset + self.size
+    }
+}
+
+pub fn align_up(value: u64, alignment: u64) -> u64 {
+    if alignment <= 1 {
+        return value;
+    }
+    value.div_ceil(alignment) * alignment
+}
+
+/// sub-allocates a range of memory
+/// it keeps linear and optimal resources at least `granularity` bytes apart,
+/// as required by `bufferImageGranularity`
+#[derive(Debug, Clone)]
+pub struct FreeList {
+    size: u64,
+    granularity: u64,
+    used: u64,
+    /// sorted by offset, together they cover the whole range
+    chunks: Vec<Chunk>,
+}
+
+impl FreeList {
+    pub fn new(size: u64, granularity: u64) -> Self {
+        Self {
+            size,
+            granularity: granularity.max(1),
+            used: 0,
+            chunks: vec![Chunk {
+                offset: 0,
+                size,
+                kind: None,
+            }],
+        }
+    }
+
+    fn same_page(&self, last_byte: u64, first_byte: u64) -> bool {
+        last_byte / self.granularity == first_byte / self.granularity
+    }
+
+    fn conflicts(&self, neighbour: Option<&Chunk>, kind: AllocationKind) -> bool {
+        neighbour.is_some_and(|v| v.kind.is_some_and(|v| v != kind))
+    }
+
+    /// where an allocation would start inside of the free chunk at `index`
+    fn fit(&self, index: usize, size: u64, alignment: u64, kind: AllocationKind) -> Option<u64> {
+        let chunk = &self.chunks[index];
+        let mut start = align_up(chunk.offset, alignment);
+
+        let prev = index.checked_sub(1).map(|i| &self.chunks[i]);
+        if self.conflicts(prev, kind) && self.same_page(chunk.offset - 1, start) {
+            start = align_up(start, self.granularity);
+        }
+
+        let end = start + size;
+        if end > chunk.end() {
+            return None;
+        }
+
+        let next = self.chunks.get(index + 1);
+        if self.conflicts(next, kind) && self.same_page(end - 1, chunk.end()) {
+            return None;
+        }
+
+        Some(start)
+    }
+
+    /// returns the offset of the allocation or `None` if it doesn't fit
+    pub fn allocate(&mut self, size: u64, alignment: u64, kind: AllocationKind) -> Option<u64> {
+        if size == 0 {
+            return None;
+        }
+
+        // best fit, the smallest free chunk the allocation fits into
+        let (index, start) = (0..self.chunks.len())
+            .filter(|&i| self.chunks[i].kind.is_none())
+            .filter_map(|i| Some((i, self.fit(i, size, alignment, kind)?)))
+            .min_by_key(|&(i, _)| self.chunks[i].size)?;
+
+        let chunk = self.chunks[index];
+        let end = start + size;
+
+        let mut replacement = Vec::with_capacity(3);
+        if start > chunk.offset {
+            replacement.push(Chunk {
+                offset: chunk.offset,
+                size: start - chunk.offset,
+                kind: None,
+            });
+        }
+        replacement.push(Chunk {
+            offset: start,
+            size,
+            kind: Some(kind),
+        });
+        if end < chunk.end() {
+            replacement.push(Chunk {
+                offset: end,
+                size: chunk.end() - end,
+                kind: None,
+            });
+        }
+
+        self.chunks.splice(index..=index, replacement);
+        self.used += size;
+
+        Some(start)
+    }
+
+    /// frees the allocation starting at `offset`, returns false if there is none
+    pub fn free(&mut self, offset: u64) -> bool {
+        let Ok(mut index) = self.chunks.binary_search_by_key(&offset, |v| v.offset) else {
+            return false;
+        };
+        if self.chunks[index].kind.take().is_none() {
+            return false;
+        }
+        self.used -= self.chunks[index].size;
+
+        if self.chunks.get(index + 1).is_some_and(|v| v.kind.is_none()) {
+            let next = self.chunks.remove(index + 1);
+            self.chunks[index].size += next.size;
+        }
+        if index > 0 && self.chunks[index - 1].kind.is_none() {
+            let current = self.chunks.remove(index);
+            index -= 1;
+            self.chunks[index].size += current.size;
+        }
+
+        true
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// bytes handed out to allocations, without alignment padding
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.used == 0
+    }
+
+    pub fn allocation_count(&self) -> usize {
+        self.chunks.iter().filter(|v| v.kind.is_some()).count()
+    }
+
+    pub fn largest_free_range(&self) -> u64 {
+        self.chunks
+            .iter()
+            .filter(|v| v.kind.is_none())
+            .map(|v| v.size)
+            .max()
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,153 @@
+mod allocator;
+mod free_list;
+mod stats;
+
+pub use allocator::*;
+pub use free_list::*;
+pub use stats::*;
+
+use std::ptr::NonNull;
+
+use ash::vk;
+
+/// buffers and linear images need to be kept `bufferImageGranularity` apart from optimal images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocationKind {
+    Linear,
+    Optimal,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AllocationRequest {
+    pub requirements: vk::MemoryRequirements,
+    pub visibility: vk::MemoryPropertyFlags,
+    pub kind: AllocationKind,
+    /// always give the resource its own `vk::DeviceMemory`
+    pub dedicated: bool,
+    pub category: MemoryCategory,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MappedPtr(NonNull<u8>);
+
+// the pointer is only handed out through `Allocation::mapped_ptr`, the memory it points to
+// is synchronized by the owner of the allocation
+unsafe impl Send for MappedPtr {}
+unsafe impl Sync for MappedPtr {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AllocationSource {
+    Block(usize),
+    Dedicated,
+}
+
+/// a range of device memory handed out by the `MemoryAllocator`
+/// it has to be given back with `Device::free_memory` before it's dropped
+#[derive(Debug)]
+pub struct Allocation {
+    memory: vk::DeviceMemory,
+    offset: u64,
+    size: u64,
+    memory_type_index: u32,
+    mapped: Option<MappedPtr>,
+    coherent: bool,
+    source: AllocationSource,
+    category: MemoryCategory,
+}
+
+impl Allocation {
+    pub fn memory(&self) -> vk::DeviceMemory {
+        self.memory
+    }
+    /// offset inside of `memory()`
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn memory_type_index(&self) -> u32 {
+        self.memory_type_index
+    }
+    pub fn category(&self) -> MemoryCategory {
+        self.category
+    }
+    pub fn is_dedicated(&self) -> bool {
+        self.source == AllocationSource::Dedicated
+    }
+    /// false if writes from the host need `Device::flush_memory` and reads `Device::invalidate_memory`
+    pub fn is_coherent(&self) -> bool {
+        self.coherent
+    }
+    /// pointer to the start of the allocation if the memory is host visible
+    /// host visible memory stays mapped for as long as it's allocated
+    pub fn mapped_ptr(&self) -> Option<NonNull<u8>> {
+        self.mapped.map(|v| v.0)
+    }
+}
+
+/// the raw memory calls used by the allocator
+/// implemented for `ash::Device`, tests can provide their own to run without a GPU
+pub trait MemoryBackend {
+    fn allocate_memory(
+        &self,
+        memory_type_index: u32,
+        size: u64,
+    ) -> Result<vk::DeviceMemory, vk::Result>;
+    fn map_memory(&self, memory: vk::DeviceMemory) -> Result<NonNull<u8>, vk::Result>;
+    /// `mapped` is true if the memory was mapped with `map_memory`
+    fn free_memory(&self, memory: vk::DeviceMemory, mapped: bool);
+}
+
+impl MemoryBackend for ash::Device {
+    fn allocate_memory(
+        &self,
+        memory_type_index: u32,
+        size: u64,
+    ) -> Result<vk::DeviceMemory, vk::Result> {
+        let info = vk::MemoryAllocateInfo::default()
+            .allocation_size(size)
+            .memory_type_index(memory_type_index);
+
+        unsafe { ash::Device::allocate_memory(self, &info, None) }
+    }
+
+    fn map_memory(&self, memory: vk::DeviceMemory) -> Result<NonNull<u8>, vk::Result> {
+        let ptr = unsafe {
+            ash::Device::map_memory(self, memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+        }?;
+        NonNull::new(ptr.cast()).ok_or(vk::Result::ERROR_MEMORY_MAP_FAILED)
+    }
+
+    fn free_memory(&self, memory: vk::DeviceMemory, mapped: bool) {
+        unsafe {
+            if mapped {
+                self.unmap_memory(memory);
+            }
+            ash::Device::free_memory(self, memory, None);
+        }
+    }
+}
+
+/// the `(offset, size)` that has to be flushed or invalidated for the bytes `offset..offset + size`
+/// of non-coherent memory, both are multiples of `atom_size`
+pub fn non_coherent_range(offset: u64, size: u64, atom_size: u64) -> (u64, u64) {
+    let start = offset / atom_size * atom_size;
+    let end = align_up(offset + size, atom_size);
+    (start, end - start)
+}
+
+pub fn find_memorytype_index(
+    memory_req: &vk::MemoryRequirements,
+    memory_prop: &vk::PhysicalDeviceMemoryProperties,
+    flags: vk::MemoryPropertyFlags,
+) -> Option<u32> {
+    memory_prop.memory_types[..memory_prop.memory_type_count as _]
+        .iter()
+        .enumerate()
+        .find(|(index, memory_type)| {
+            (1 << index) & memory_req.memory_type_bits != 0
+                && memory_type.property_flags & flags == flags
+        })
+        .map(|(index, _memory_type)| index as _)
+}
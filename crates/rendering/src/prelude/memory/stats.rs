@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+
+use ash::vk;
+
+/// what an allocation is used for, allocations are grouped by it in the `MemoryReport`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MemoryCategory {
+    Buffer,
+    Image,
+    Staging,
+    Readback,
+    Named(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CategoryUsage {
+    pub bytes: u64,
+    pub allocation_count: usize,
+}
+
+impl CategoryUsage {
+    pub(crate) fn add(&mut self, bytes: u64) {
+        self.bytes += bytes;
+        self.allocation_count += 1;
+    }
+
+    pub(crate) fn remove(&mut self, bytes: u64) {
+        self.bytes -= bytes;
+        self.allocation_count -= 1;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HeapReport {
+    pub heap_index: u32,
+    pub flags: vk::MemoryHeapFlags,
+    pub size: u64,
+    /// bytes of `vk::DeviceMemory` the allocator holds on this heap
+    pub allocated: u64,
+    /// bytes handed out to live allocations
+    pub used: u64,
+    pub block_count: usize,
+    pub dedicated_count: usize,
+    pub allocation_count: usize,
+    /// 0 if the free memory inside of the blocks is one contiguous range, close to 1 if it's scattered
+    pub fragmentation: f32,
+    pub categories: BTreeMap<MemoryCategory, CategoryUsage>,
+    /// how much this process can allocate on the heap, `None` without `VK_EXT_memory_budget`
+    pub budget: Option<u64>,
+    /// usage of the whole process as seen by the driver, `None` without `VK_EXT_memory_budget`
+    pub process_usage: Option<u64>,
+}
+
+impl HeapReport {
+    pub fn is_device_local(&self) -> bool {
+        self.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryReport {
+    pub heaps: Vec<HeapReport>,
+}
+
+impl MemoryReport {
+    pub fn allocated(&self) -> u64 {
+        self.heaps.iter().map(|v| v.allocated).sum()
+    }
+
+    pub fn used(&self) -> u64 {
+        self.heaps.iter().map(|v| v.used).sum()
+    }
+
+    pub fn allocation_count(&self) -> usize {
+        self.heaps.iter().map(|v| v.allocation_count).sum()
+    }
+
+    /// usage of a category summed over all heaps
+    pub fn category(&self, category: MemoryCategory) -> CategoryUsage {
+        self.heaps
+            .iter()
+            .filter_map(|v| v.categories.get(&category))
+            .fold(CategoryUsage::default(), |total, v| CategoryUsage {
+                bytes: total.bytes + v.bytes,
+                allocation_count: total.allocation_count + v.allocation_count,
+            })
+    }
+
+    /// heaps where the process uses more memory than the driver budget allows
+    pub fn over_budget(&self) -> impl Iterator<Item = &HeapReport> {
+        self.heaps
+            .iter()
+            .filter(|v| v.process_usage.zip(v.budget).is_some_and(|(u, b)| u > b))
+    }
+}
@@ -2,23 +2,34 @@ mod instance;
 mod device;
 mod swapchain;
 mod command_buffer;
-mod fence;
+mod sync;
 mod image;
 mod debugger;
+mod frame;
 mod buffer;
 mod descriptors;
+mod memory;
 mod pipeline;
 mod render_pass;
+mod sampler;
+mod shader;
+mod transfer;
 
 pub use instance::*;
 pub use device::*;
 pub use swapchain::*;
-pub use fence::*;
+pub use sync::*;
+pub use image::*;
 pub use buffer::*;
 pub use debugger::*;
+pub use frame::*;
 pub use descriptors::*;
+pub use memory::*;
 pub use pipeline::*;
 pub use render_pass::*;
+pub use sampler::*;
+pub use shader::*;
+pub use transfer::*;
 
 pub use command_buffer::*;
 
@@ -0,0 +1,94 @@
+use std::{ffi::CString, sync::Arc};
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::{DescriptorSetLayout, Device, ShaderModule};
+
+use super::{create_pipeline_layout, Pipeline};
+
+pub struct ComputePipeline {
+    handle: vk::Pipeline,
+    layout: vk::PipelineLayout,
+    device: Arc<Device>,
+    _set_layouts: Vec<Arc<DescriptorSetLayout>>,
+    shader: Arc<ShaderModule>,
+}
+
+impl ComputePipeline {
+    pub fn new(
+        device: Arc<Device>,
+        shader: Arc<ShaderModule>,
+        entry_point: &str,
+        set_layouts: &[Arc<DescriptorSetLayout>],
+        push_constant_ranges: &[vk::PushConstantRange],
+    ) -> Result<Arc<Self>> {
+        let stage = shader.entry_point(entry_point)?.stage;
+        if stage != vk::ShaderStageFlags::COMPUTE {
+            bail!("entry point {entry_point:?} is a {stage:?} shader, expected a compute shader");
+        }
+
+        let entry_point = CString::new(entry_point)?;
+
+        let layout = create_pipeline_layout(&device, set_layouts, push_constant_ranges)?;
+
+        let stage = vk::PipelineShaderStageCreateInfo::default()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(*shader.as_raw())
+            .name(&entry_point);
+
+        let info = vk::ComputePipelineCreateInfo::default()
+            .stage(stage)
+            .layout(layout);
+
+        let result = unsafe {
+            device
+                .as_raw()
+                .create_compute_pipelines(vk::PipelineCache::null(), &[info], None)
+        };
+
+        let handle = match result {
+            Ok(pipelines) => pipelines[0],
+            Err((_, err)) => {
+                unsafe { device.as_raw().destroy_pipeline_layout(layout, None) };
+                return Err(err.into());
+            }
+        };
+
+        Ok(Self {
+            handle,
+            layout,
+            device,
+            _set_layouts: set_layouts.to_vec(),
+            shader,
+        }
+        .into())
+    }
+
+    pub fn shader(&self) -> &Arc<ShaderModule> {
+        &self.shader
+    }
+}
+
+impl Pipeline for ComputePipeline {
+    fn as_raw(&self) -> &vk::Pipeline {
+        &self.handle
+    }
+    fn layout(&self) -> &vk::PipelineLayout {
+        &self.layout
+    }
+    fn bind_point(&self) -> vk::PipelineBindPoint {
+        vk::PipelineBindPoint::COMPUTE
+    }
+}
+
+impl Drop for ComputePipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.as_raw().destroy_pipeline(self.handle, None);
+            self.device
+                .as_raw()
+                .destroy_pipeline_layout(self.layout, None);
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use std::{ffi::CString, sync::Arc};
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::{DescriptorSetLayout, Device, RenderPass, ShaderModule};
+
+use super::{create_pipeline_layout, Pipeline};
+
+pub struct GraphicsPipeline {
+    handle: vk::Pipeline,
+    layout: vk::PipelineLayout,
+    device: Arc<Device>,
+    // the pipeline keeps its layouts and render pass alive, they must outlive it
+    _set_layouts: Vec<Arc<DescriptorSetLayout>>,
+    _render_pass: Option<Arc<RenderPass>>,
+    shaders: Vec<Arc<ShaderModule>>,
+}
+
+struct ShaderStage {
+    stage: vk::ShaderStageFlags,
+    module: Arc<ShaderModule>,
+    entry_point: CString,
+}
+
+/// where the pipeline is going to render to
+pub enum PipelineRendering {
+    RenderPass {
+        render_pass: Arc<RenderPass>,
+        subpass: u32,
+    },
+    /// used with `CommandBuffer::begin_rendering`
+    Dynamic {
+        color_formats: Vec<vk::Format>,
+        depth_format: vk::Format,
+        stencil_format: vk::Format,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RasterizationState {
+    pub polygon_mode: vk::PolygonMode,
+    pub cull_mode: vk::CullModeFlags,
+    pub front_face: vk::FrontFace,
+    pub line_width: f32,
+    pub depth_clamp: bool,
+}
+
+impl Default for RasterizationState {
+    fn default() -> Self {
+        Self {
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vk::CullModeFlags::NONE,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+            line_width: 1.0,
+            depth_clamp: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DepthStencilState {
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub compare_op: vk::CompareOp,
+    pub stencil_test: bool,
+    pub front: vk::StencilOpState,
+    pub back: vk::StencilOpState,
+}
+
+impl Default for DepthStencilState {
+    fn default() -> Self {
+        Self {
+            depth_test: true,
+            depth_write: true,
+            compare_op: vk::CompareOp::LESS_OR_EQUAL,
+            stencil_test: false,
+            front: vk::StencilOpState::default(),
+            back: vk::StencilOpState::default(),
+        }
+    }
+}
+
+/// a blend state that just overwrites the target
+pub fn opaque_blend_attachment() -> vk::PipelineColorBlendAttachmentState {
+    vk::PipelineColorBlendAttachmentState::default()
+        .blend_enable(false)
+        .color_write_mask(vk::ColorComponentFlags::RGBA)
+}
+
+/// standard `src * alpha + dst * (1 - alpha)` blending
+pub fn alpha_blend_attachment() -> vk::PipelineColorBlendAttachmentState {
+    vk::PipelineColorBlendAttachmentState::default()
+        .blend_enable(true)
+        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .color_blend_op(vk::BlendOp::ADD)
+        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+        .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+        .alpha_blend_op(vk::BlendOp::ADD)
+        .color_write_mask(vk::ColorComponentFlags::RGBA)
+}
+
+pub struct GraphicsPipelineBuilder {
+    stages: Vec<ShaderStage>,
+    vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+    vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    topology: vk::PrimitiveTopology,
+    rasterization: RasterizationState,
+    samples: vk::SampleCountFlags,
+    blend_attachments: Vec<vk::PipelineColorBlendAttachmentState>,
+    depth_stencil: Option<DepthStencilState>,
+    dynamic_states: Vec<vk::DynamicState>,
+    set_layouts: Vec<Arc<DescriptorSetLayout>>,
+    push_constant_ranges: Vec<vk::PushConstantRange>,
+    rendering: Option<PipelineRendering>,
+}
+
+impl Default for GraphicsPipelineBuilder {
+    fn default() -> Self {
+        Self {
+            stages: vec![],
+            vertex_bindings: vec![],
+            vertex_attributes: vec![],
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            rasterization: RasterizationState::default(),
+            samples: vk::SampleCountFlags::TYPE_1,
+            blend_attachments: vec![],
+            depth_stencil: None,
+            // viewport and scissor are always dynamic so the pipeline survives a resize
+            dynamic_states: vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR],
+            set_layouts: vec![],
+            push_constant_ranges: vec![],
+            rendering: None,
+        }
+    }
+}
+
+impl GraphicsPipelineBuilder {
+    /// the stage is taken from the entry point declared in the module
+    pub fn shader_stage(mut self, module: Arc<ShaderModule>, entry_point: &str) -> Result<Self> {
+        let stage = module.entry_point(entry_point)?.stage;
+
+        if stage == vk::ShaderStageFlags::COMPUTE {
+            bail!("entry point {entry_point:?} is a compute shader");
+        }
+        if self.stages.iter().any(|v| v.stage == stage) {
+            bail!("the pipeline already has a {stage:?} stage");
+        }
+
+        self.stages.push(ShaderStage {
+            stage,
+            module,
+            entry_point: CString::new(entry_point)?,
+        });
+        Ok(self)
+    }
+
+    pub fn vertex_binding(
+        mut self,
+        binding: u32,
+        stride: u32,
+        input_rate: vk::VertexInputRate,
+    ) -> Self {
+        self.vertex_bindings
+            .push(vk::VertexInputBindingDescription {
+                binding,
+                stride,
+                input_rate,
+            });
+        self
+    }
+
+    pub fn vertex_attribute(
+        mut self,
+        location: u32,
+        binding: u32,
+        format: vk::Format,
+        offset: u32,
+    ) -> Self {
+        self.vertex_attributes
+            .push(vk::VertexInputAttributeDescription {
+                location,
+                binding,
+                format,
+                offset,
+            });
+        self
+    }
+
+    pub fn topology(mut self, topology: vk::PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn rasterization(mut self, state: RasterizationState) -> Self {
+        self.rasterization = state;
+        self
+    }
+
+    pub fn samples(mut self, samples: vk::SampleCountFlags) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// one blend state per color attachment
+    /// if none are given every attachment uses `opaque_blend_attachment`
+    pub fn color_blend_attachment(mut self, state: vk::PipelineColorBlendAttachmentState) -> Self {
+        self.blend_attachments.push(state);
+        self
+    }
+
+    pub fn depth_stencil(mut self, state: DepthStencilState) -> Self {
+        self.depth_stencil = Some(state);
+        self
+    }
+
+    pub fn dynamic_state(mut self, state: vk::DynamicState) -> Self {
+        if !self.dynamic_states.contains(&state) {
+            self.dynamic_states.push(state);
+        }
+        self
+    }
+
+    pub fn descriptor_set_layout(mut self, layout: Arc<DescriptorSetLayout>) -> Self {
+        self.set_layouts.push(layout);
+        self
+    }
+
+    pub fn push_constant_range(mut self, range: vk::PushConstantRange) -> Self {
+        self.push_constant_ranges.push(range);
+        self
+    }
+
+    pub fn render_pass(mut self, render_pass: Arc<RenderPass>, subpass: u32) -> Self {
+        self.rendering = Some(PipelineRendering::RenderPass {
+            render_pass,
+            subpass,
+        });
+        self
+    }
+
+    pub fn dynamic_rendering(
+        mut self,
+        color_formats: &[vk::Format],
+        depth_format: vk::Format,
+        stencil_format: vk::Format,
+    ) -> Self {
+        self.rendering = Some(PipelineRendering::Dynamic {
+            color_formats: color_formats.to_vec(),
+            depth_format,
+            stencil_format,
+        });
+        self
+    }
+
+    pub fn build(self, device: Arc<Device>) -> Result<Arc<GraphicsPipeline>> {
+        if self.stages.is_empty() {
+            bail!("a graphics pipeline needs at least one shader stage");
+        }
+
+        let Some(rendering) = self.rendering else {
+            bail!("a graphics pipeline needs either a render pass or dynamic rendering formats");
+        };
+
+        let color_attachment_count = match &rendering {
+            PipelineRendering::RenderPass {
+                render_pass,
+                subpass,
+            } => render_pass.color_attachment_count(*subpass)?,
+            PipelineRendering::Dynamic { color_formats, .. } => color_formats.len(),
+        };
+
+        let blend_attachments = if self.blend_attachments.is_empty() {
+            vec![opaque_blend_attachment(); color_attachment_count]
+        } else if self.blend_attachments.len() == color_attachment_count {
+            self.blend_attachments
+        } else {
+            bail!(
+                "got {} blend attachments but the pipeline renders to {} color attachments",
+                self.blend_attachments.len(),
+                color_attachment_count
+            );
+        };
+
+        let stages: Vec<_> = self
+            .stages
+            .iter()
+            .map(|stage| {
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(stage.stage)
+                    .module(*stage.module.as_raw())
+                    .name(&stage.entry_point)
+            })
+            .collect();
+
+        let vertex_input = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&self.vertex_bindings)
+            .vertex_attribute_descriptions(&self.vertex_attributes);
+
+        let input_assembly =
+            vk::PipelineInputAssemblyStateCreateInfo::default().topology(self.topology);
+
+        let viewport = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        let raster = self.rasterization;
+        let rasterization = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(raster.polygon_mode)
+            .cull_mode(raster.cull_mode)
+            .front_face(raster.front_face)
+            .line_width(raster.line_width)
+            .depth_clamp_enable(raster.depth_clamp);
+
+        let multisample =
+            vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(self.samples);
+
+        let color_blend =
+            vk::PipelineColorBlendStateCreateInfo::default().attachments(&blend_attachments);
+
+        let depth_stencil = self.depth_stencil.map(|state| {
+            vk::PipelineDepthStencilStateCreateInfo::default()
+                .depth_test_enable(state.depth_test)
+                .depth_write_enable(state.depth_write)
+                .depth_compare_op(state.compare_op)
+                .stencil_test_enable(state.stencil_test)
+                .front(state.front)
+                .back(state.back)
+                .max_depth_bounds(1.0)
+        });
+
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&self.dynamic_states);
+
+        let layout =
+            create_pipeline_layout(&device, &self.set_layouts, &self.push_constant_ranges)?;
+
+        let mut info = vk::GraphicsPipelineCreateInfo::default()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport)
+            .rasterization_state(&rasterization)
+            .multisample_state(&multisample)
+            .color_blend_state(&color_blend)
+            .dynamic_state(&dynamic_state)
+            .layout(layout);
+
+        if let Some(depth_stencil) = &depth_stencil {
+            info = info.depth_stencil_state(depth_stencil);
+        }
+
+        let mut rendering_info;
+        let render_pass = match &rendering {
+            PipelineRendering::RenderPass {
+                render_pass,
+                subpass,
+            } => {
+                info = info.render_pass(*render_pass.as_raw()).subpass(*subpass);
+                Some(render_pass.clone())
+            }
+            PipelineRendering::Dynamic {
+                color_formats,
+                depth_format,
+                stencil_format,
+            } => {
+                rendering_info = vk::PipelineRenderingCreateInfo::default()
+                    .color_attachment_formats(color_formats)
+                    .depth_attachment_format(*depth_format)
+                    .stencil_attachment_format(*stencil_format);
+                info = info.push_next(&mut rendering_info);
+                None
+            }
+        };
+
+        let result = unsafe {
+            device
+                .as_raw()
+                .create_graphics_pipelines(vk::PipelineCache::null(), &[info], None)
+        };
+
+        let handle = match result {
+            Ok(pipelines) => pipelines[0],
+            Err((_, err)) => {
+                unsafe { device.as_raw().destroy_pipeline_layout(layout, None) };
+                return Err(err.into());
+            }
+        };
+
+        Ok(GraphicsPipeline {
+            handle,
+            layout,
+            device,
+            _set_layouts: self.set_layouts,
+            _render_pass: render_pass,
+            shaders: self.stages.into_iter().map(|v| v.module).collect(),
+        }
+        .into())
+    }
+}
+
+impl GraphicsPipeline {
+    pub fn builder() -> GraphicsPipelineBuilder {
+        GraphicsPipelineBuilder::default()
+    }
+
+    pub fn shaders(&self) -> &[Arc<ShaderModule>] {
+        &self.shaders
+    }
+}
+
+impl Pipeline for GraphicsPipeline {
+    fn as_raw(&self) -> &vk::Pipeline {
+        &self.handle
+    }
+    fn layout(&self) -> &vk::PipelineLayout {
+        &self.layout
+    }
+    fn bind_point(&self) -> vk::PipelineBindPoint {
+        vk::PipelineBindPoint::GRAPHICS
+    }
+}
+
+impl Drop for GraphicsPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.as_raw().destroy_pipeline(self.handle, None);
+            self.device
+                .as_raw()
+                .destroy_pipeline_layout(self.layout, None);
+        }
+    }
+}
@@ -1,23 +1,34 @@
+mod compute;
+mod graphics;
+
+pub use compute::*;
+pub use graphics::*;
+
+use std::sync::Arc;
+
+use anyhow::Result;
 use ash::vk;
 
-#[allow(unused)]
-pub fn create_test_pipeline() {
-    let color_attachment = [vk::AttachmentReference {
-        attachment: 0,
-        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-    }];
-
-    let dependencies = [vk::SubpassDependency {
-        src_subpass: vk::SUBPASS_EXTERNAL,
-        src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-        dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ
-            | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
-        dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-        ..Default::default()
-    }];
-
-    // let subpass = vk::SubpassDescription::default()
-    //     .color_attachments(&color_attachment)
-    //     .depth_stencil_attachment(&depth_attachment)
-    //     .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS);
+use crate::prelude::{DescriptorSetLayout, Device};
+
+pub use vk::{PrimitiveTopology, PushConstantRange};
+
+pub trait Pipeline {
+    fn as_raw(&self) -> &vk::Pipeline;
+    fn layout(&self) -> &vk::PipelineLayout;
+    fn bind_point(&self) -> vk::PipelineBindPoint;
+}
+
+fn create_pipeline_layout(
+    device: &Device,
+    set_layouts: &[Arc<DescriptorSetLayout>],
+    push_constant_ranges: &[vk::PushConstantRange],
+) -> Result<vk::PipelineLayout> {
+    let raw_layouts: Vec<_> = set_layouts.iter().map(|v| *v.as_raw()).collect();
+
+    let info = vk::PipelineLayoutCreateInfo::default()
+        .set_layouts(&raw_layouts)
+        .push_constant_ranges(push_constant_ranges);
+
+    Ok(unsafe { device.as_raw().create_pipeline_layout(&info, None) }?)
 }
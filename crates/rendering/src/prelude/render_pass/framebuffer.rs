@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::{Device, ImageView, RenderPass};
+
+pub struct Framebuffer {
+    handle: vk::Framebuffer,
+    device: Arc<Device>,
+    // the render pass has to outlive every framebuffer made for it
+    render_pass: Arc<RenderPass>,
+    // the views have to outlive the framebuffer as well
+    attachments: Vec<Arc<ImageView>>,
+    extent: vk::Extent2D,
+}
+
+impl Framebuffer {
+    /// `attachments` need one view per attachment of the render pass, in the same order
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<RenderPass>,
+        attachments: &[Arc<ImageView>],
+        extent: vk::Extent2D,
+    ) -> Result<Arc<Self>> {
+        if attachments.len() != render_pass.attachments().len() {
+            bail!(
+                "render pass has {} attachments, got {} views",
+                render_pass.attachments().len(),
+                attachments.len()
+            );
+        }
+
+        let views: Vec<_> = attachments.iter().map(|v| *v.as_raw()).collect();
+        let info = vk::FramebufferCreateInfo::default()
+            .render_pass(*render_pass.as_raw())
+            .attachments(&views)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let handle = unsafe { device.as_raw().create_framebuffer(&info, None) }?;
+
+        Ok(Self {
+            handle,
+            device,
+            render_pass,
+            attachments: attachments.to_vec(),
+            extent,
+        }
+        .into())
+    }
+
+    pub fn render_pass(&self) -> &Arc<RenderPass> {
+        &self.render_pass
+    }
+
+    pub fn attachments(&self) -> &[Arc<ImageView>] {
+        &self.attachments
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    pub fn as_raw(&self) -> &vk::Framebuffer {
+        &self.handle
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe { self.device.as_raw().destroy_framebuffer(self.handle, None) };
+    }
+}
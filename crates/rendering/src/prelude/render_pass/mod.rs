@@ -1,4 +1,7 @@
-use anyhow::Result;
+mod framebuffer;
+pub use framebuffer::*;
+
+use anyhow::{bail, Result};
 use std::sync::Arc;
 
 use crate::prelude::Device;
@@ -46,6 +49,21 @@ impl RenderPass {
         }
         .into())
     }
+
+    pub fn as_raw(&self) -> &vk::RenderPass {
+        &self.handle
+    }
+
+    pub fn color_attachment_count(&self, subpass: u32) -> Result<usize> {
+        if subpass != 0 {
+            bail!("render pass only has one subpass, got subpass {subpass}");
+        }
+        Ok(self.attachments_refs.len())
+    }
+
+    pub fn attachments(&self) -> &[vk::AttachmentDescription] {
+        &self.attachment_decriptors
+    }
 }
 
 impl Drop for RenderPass {
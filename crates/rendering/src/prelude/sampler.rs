@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::Device;
+
+pub use vk::{BorderColor, CompareOp, Filter, SamplerAddressMode, SamplerMipmapMode};
+
+pub struct Sampler {
+    handle: vk::Sampler,
+    device: Arc<Device>,
+}
+
+impl Sampler {
+    pub fn builder() -> SamplerBuilder {
+        SamplerBuilder::default()
+    }
+
+    pub fn as_raw(&self) -> &vk::Sampler {
+        &self.handle
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe { self.device.as_raw().destroy_sampler(self.handle, None) };
+    }
+}
+
+/// defaults to linear filtering with repeating texture coordinates and all mip levels
+#[derive(Clone, Copy, Debug)]
+pub struct SamplerBuilder {
+    mag_filter: vk::Filter,
+    min_filter: vk::Filter,
+    mipmap_mode: vk::SamplerMipmapMode,
+    address_modes: [vk::SamplerAddressMode; 3],
+    mip_lod_bias: f32,
+    max_anisotropy: Option<f32>,
+    compare_op: Option<vk::CompareOp>,
+    min_lod: f32,
+    max_lod: f32,
+    border_color: vk::BorderColor,
+    unnormalized_coordinates: bool,
+}
+
+impl Default for SamplerBuilder {
+    fn default() -> Self {
+        Self {
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+            address_modes: [vk::SamplerAddressMode::REPEAT; 3],
+            mip_lod_bias: 0.0,
+            max_anisotropy: None,
+            compare_op: None,
+            min_lod: 0.0,
+            max_lod: vk::LOD_CLAMP_NONE,
+            border_color: vk::BorderColor::FLOAT_TRANSPARENT_BLACK,
+            unnormalized_coordinates: false,
+        }
+    }
+}
+
+impl SamplerBuilder {
+    /// sets both the magnification and minification filter
+    pub fn filter(mut self, filter: vk::Filter) -> Self {
+        self.mag_filter = filter;
+        self.min_filter = filter;
+        self
+    }
+
+    pub fn mag_filter(mut self, filter: vk::Filter) -> Self {
+        self.mag_filter = filter;
+        self
+    }
+
+    pub fn min_filter(mut self, filter: vk::Filter) -> Self {
+        self.min_filter = filter;
+        self
+    }
+
+    pub fn mipmap_mode(mut self, mode: vk::SamplerMipmapMode) -> Self {
+        self.mipmap_mode = mode;
+        self
+    }
+
+    /// sets the address mode of all three coordinates
+    pub fn address_mode(mut self, mode: vk::SamplerAddressMode) -> Self {
+        self.address_modes = [mode; 3];
+        self
+    }
+
+    pub fn address_modes(
+        mut self,
+        u: vk::SamplerAddressMode,
+        v: vk::SamplerAddressMode,
+        w: vk::SamplerAddressMode,
+    ) -> Self {
+        self.address_modes = [u, v, w];
+        self
+    }
+
+    pub fn mip_lod_bias(mut self, bias: f32) -> Self {
+        self.mip_lod_bias = bias;
+        self
+    }
+
+    pub fn lod_range(mut self, min_lod: f32, max_lod: f32) -> Self {
+        self.min_lod = min_lod;
+        self.max_lod = max_lod;
+        self
+    }
+
+    /// clamped to `maxSamplerAnisotropy` of the device
+    /// needs the `samplerAnisotropy` feature, which is enabled when the device supports it
+    pub fn anisotropy(mut self, max_anisotropy: f32) -> Self {
+        self.max_anisotropy = Some(max_anisotropy);
+        self
+    }
+
+    /// makes this a comparison sampler, e.g. for shadow maps
+    pub fn compare_op(mut self, op: vk::CompareOp) -> Self {
+        self.compare_op = Some(op);
+        self
+    }
+
+    /// used with `SamplerAddressMode::CLAMP_TO_BORDER`
+    pub fn border_color(mut self, color: vk::BorderColor) -> Self {
+        self.border_color = color;
+        self
+    }
+
+    pub fn unnormalized_coordinates(mut self, unnormalized: bool) -> Self {
+        self.unnormalized_coordinates = unnormalized;
+        self
+    }
+
+    pub fn build(self, device: Arc<Device>) -> Result<Arc<Sampler>> {
+        if self.min_lod > self.max_lod {
+            bail!(
+                "min lod {} is bigger than max lod {}",
+                self.min_lod,
+                self.max_lod
+            );
+        }
+
+        if self.max_anisotropy.is_some()
+            && device.enabled_features().sampler_anisotropy != vk::TRUE
+        {
+            bail!("anisotropic filtering needs the samplerAnisotropy feature");
+        }
+
+        let max_anisotropy = self
+            .max_anisotropy
+            .map(|v| v.clamp(1.0, device.properties().limits.max_sampler_anisotropy));
+
+        let info = vk::SamplerCreateInfo::default()
+            .mag_filter(self.mag_filter)
+            .min_filter(self.min_filter)
+            .mipmap_mode(self.mipmap_mode)
+            .address_mode_u(self.address_modes[0])
+            .address_mode_v(self.address_modes[1])
+            .address_mode_w(self.address_modes[2])
+            .mip_lod_bias(self.mip_lod_bias)
+            .anisotropy_enable(max_anisotropy.is_some())
+            .max_anisotropy(max_anisotropy.unwrap_or(1.0))
+            .compare_enable(self.compare_op.is_some())
+            .compare_op(self.compare_op.unwrap_or(vk::CompareOp::NEVER))
+            .min_lod(self.min_lod)
+            .max_lod(self.max_lod)
+            .border_color(self.border_color)
+            .unnormalized_coordinates(self.unnormalized_coordinates);
+
+        let handle = unsafe { device.as_raw().create_sampler(&info, None) }?;
+
+        Ok(Sampler { handle, device }.into())
+    }
+}
@@ -0,0 +1,88 @@
+mod reflect;
+mod spirv;
+mod watcher;
+
+pub use reflect::*;
+pub use spirv::{entry_points, spirv_words, EntryPoint, SPIRV_MAGIC};
+pub use watcher::*;
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use ash::vk;
+
+use crate::prelude::Device;
+
+pub struct ShaderModule {
+    handle: vk::ShaderModule,
+    device: Arc<Device>,
+    entry_points: Vec<EntryPoint>,
+    code: Vec<u32>,
+}
+
+impl ShaderModule {
+    pub fn from_spirv_bytes(device: Arc<Device>, bytes: &[u8]) -> Result<Arc<Self>> {
+        let code = spirv_words(bytes)?;
+        let entry_points = entry_points(&code)?;
+
+        if entry_points.is_empty() {
+            bail!("SPIR-V module doesn't declare any entry points");
+        }
+
+        let info = vk::ShaderModuleCreateInfo::default().code(&code);
+
+        let handle = unsafe { device.as_raw().create_shader_module(&info, None) }?;
+
+        Ok(Self {
+            handle,
+            device,
+            entry_points,
+            code,
+        }
+        .into())
+    }
+
+    pub fn from_file(device: Arc<Device>, path: impl AsRef<Path>) -> Result<Arc<Self>> {
+        let path = path.as_ref();
+
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("failed to read shader {}", path.display()))?;
+
+        Self::from_spirv_bytes(device, &bytes)
+            .with_context(|| format!("failed to load shader {}", path.display()))
+    }
+
+    pub fn entry_points(&self) -> &[EntryPoint] {
+        &self.entry_points
+    }
+
+    pub fn entry_point(&self, name: &str) -> Result<&EntryPoint> {
+        self.entry_points
+            .iter()
+            .find(|entry| entry.name == name)
+            .with_context(|| format!("shader module has no entry point named {name:?}"))
+    }
+
+    pub fn reflect(&self, entry_point: &str) -> Result<ShaderReflection> {
+        ShaderReflection::new(&self.code, entry_point)
+    }
+
+    /// the validated SPIR-V words in native endian
+    pub fn code(&self) -> &[u32] {
+        &self.code
+    }
+
+    pub fn as_raw(&self) -> &vk::ShaderModule {
+        &self.handle
+    }
+}
+
+impl Drop for ShaderModule {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .as_raw()
+                .destroy_shader_module(self.handle, None)
+        };
+    }
+}
@@ -0,0 +1,667 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use ash::vk;
+
+use super::spirv::{instructions, parse_string};
+use crate::prelude::{DescriptorSetLayout, DescriptorType, Device};
+
+// opcodes
+const OP_ENTRY_POINT: u16 = 15;
+const OP_TYPE_BOOL: u16 = 20;
+const OP_TYPE_INT: u16 = 21;
+const OP_TYPE_FLOAT: u16 = 22;
+const OP_TYPE_VECTOR: u16 = 23;
+const OP_TYPE_MATRIX: u16 = 24;
+const OP_TYPE_IMAGE: u16 = 25;
+const OP_TYPE_SAMPLER: u16 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u16 = 27;
+const OP_TYPE_ARRAY: u16 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u16 = 29;
+const OP_TYPE_STRUCT: u16 = 30;
+const OP_TYPE_POINTER: u16 = 32;
+const OP_CONSTANT: u16 = 43;
+const OP_VARIABLE: u16 = 59;
+const OP_DECORATE: u16 = 71;
+const OP_MEMBER_DECORATE: u16 = 72;
+const OP_TYPE_ACCELERATION_STRUCTURE: u16 = 5341;
+
+// storage classes
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+// decorations
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+
+/// before SPIR-V 1.4 entry points only list their input and output variables
+const VERSION_1_4: u32 = 0x0001_0400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexInput {
+    pub location: u32,
+    pub format: vk::Format,
+}
+
+/// the resources one entry point of a shader uses
+#[derive(Debug, Clone, Default)]
+pub struct ShaderReflection {
+    pub stage: vk::ShaderStageFlags,
+    /// bindings grouped by set index and sorted by binding
+    /// runtime sized arrays are reported with a count of 0
+    pub descriptor_sets: BTreeMap<u32, Vec<DescriptorType>>,
+    pub push_constants: Option<vk::PushConstantRange>,
+    /// only filled for vertex shaders, sorted by location
+    pub vertex_inputs: Vec<VertexInput>,
+}
+
+/// the merged resources of all stages of a pipeline
+#[derive(Debug, Clone, Default)]
+pub struct PipelineReflection {
+    pub descriptor_sets: BTreeMap<u32, Vec<DescriptorType>>,
+    pub push_constant_ranges: Vec<vk::PushConstantRange>,
+    pub vertex_inputs: Vec<VertexInput>,
+}
+
+#[derive(Debug, Clone)]
+enum Type {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage { image: u32 },
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct { members: Vec<u32> },
+    Pointer { pointee: u32 },
+    AccelerationStructure,
+}
+
+#[derive(Debug, Default)]
+struct Decorations {
+    set: Option<u32>,
+    binding: Option<u32>,
+    location: Option<u32>,
+    built_in: bool,
+    buffer_block: bool,
+    array_stride: Option<u32>,
+}
+
+#[derive(Debug, Default)]
+struct MemberDecorations {
+    offset: Option<u32>,
+    matrix_stride: Option<u32>,
+}
+
+#[derive(Default)]
+struct Module {
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    decorations: HashMap<u32, Decorations>,
+    members: HashMap<(u32, u32), MemberDecorations>,
+    // (id, pointer type, storage class)
+    variables: Vec<(u32, u32, u32)>,
+    // (name, interface) in declaration order
+    entry_points: Vec<(String, Vec<u32>)>,
+}
+
+impl Module {
+    fn ty(&self, id: u32) -> Result<&Type> {
+        self.types
+            .get(&id)
+            .with_context(|| format!("SPIR-V references unknown type %{id}"))
+    }
+
+    fn decorations(&self, id: u32) -> Option<&Decorations> {
+        self.decorations.get(&id)
+    }
+
+    fn array_length(&self, length: u32) -> Result<u32> {
+        self.constants
+            .get(&length)
+            .copied()
+            .with_context(|| format!("array length %{length} is not a constant"))
+    }
+
+    /// strips arrays from a type, returns the element type and the total element count
+    /// a count of 0 means the array is runtime sized
+    fn unwrap_arrays(&self, mut id: u32) -> Result<(u32, u32)> {
+        let mut count = 1;
+        loop {
+            match self.ty(id)? {
+                Type::Array { element, length } => {
+                    count *= self.array_length(*length)?;
+                    id = *element;
+                }
+                Type::RuntimeArray { element } => {
+                    count = 0;
+                    id = *element;
+                }
+                _ => return Ok((id, count)),
+            }
+        }
+    }
+
+    fn size_of(&self, id: u32, matrix_stride: Option<u32>) -> Result<u32> {
+        Ok(match self.ty(id)? {
+            Type::Bool => 4,
+            Type::Int { width, .. } | Type::Float { width } => width / 8,
+            Type::Vector { component, count } => count * self.size_of(*component, None)?,
+            Type::Matrix { column, count } => match matrix_stride {
+                Some(stride) => count * stride,
+                None => count * self.size_of(*column, None)?,
+            },
+            Type::Array { element, length } => {
+                let length = self.array_length(*length)?;
+                match self.decorations(id).and_then(|v| v.array_stride) {
+                    Some(stride) => length * stride,
+                    None => length * self.size_of(*element, matrix_stride)?,
+                }
+            }
+            Type::RuntimeArray { .. } => 0,
+            Type::Struct { members } => {
+                let mut size = 0;
+                for (i, member) in members.iter().enumerate() {
+                    let decorations = self.members.get(&(id, i as u32));
+                    let offset = decorations.and_then(|v| v.offset).unwrap_or(size);
+                    let stride = decorations.and_then(|v| v.matrix_stride);
+                    size = size.max(offset + self.size_of(*member, stride)?);
+                }
+                size
+            }
+            ty => bail!("can't compute the size of {ty:?}"),
+        })
+    }
+
+    fn descriptor_type(&self, id: u32, storage: u32) -> Result<vk::DescriptorType> {
+        let ty = self.ty(id)?;
+
+        Ok(match (storage, ty) {
+            (STORAGE_STORAGE_BUFFER, Type::Struct { .. }) => vk::DescriptorType::STORAGE_BUFFER,
+            (STORAGE_UNIFORM, Type::Struct { .. }) => {
+                if self.decorations(id).is_some_and(|v| v.buffer_block) {
+                    vk::DescriptorType::STORAGE_BUFFER
+                } else {
+                    vk::DescriptorType::UNIFORM_BUFFER
+                }
+            }
+            (STORAGE_UNIFORM_CONSTANT, Type::Sampler) => vk::DescriptorType::SAMPLER,
+            (STORAGE_UNIFORM_CONSTANT, Type::SampledImage { image }) => match self.ty(*image)? {
+                Type::Image {
+                    dim: DIM_BUFFER, ..
+                } => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                _ => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            },
+            (STORAGE_UNIFORM_CONSTANT, Type::Image { dim, sampled }) => match (*dim, *sampled) {
+                (DIM_SUBPASS_DATA, _) => vk::DescriptorType::INPUT_ATTACHMENT,
+                (DIM_BUFFER, 2) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+                (DIM_BUFFER, _) => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                (_, 2) => vk::DescriptorType::STORAGE_IMAGE,
+                _ => vk::DescriptorType::SAMPLED_IMAGE,
+            },
+            (STORAGE_UNIFORM_CONSTANT, Type::AccelerationStructure) => {
+                vk::DescriptorType::ACCELERATION_STRUCTURE_KHR
+            }
+            (storage, ty) => {
+                bail!("unsupported descriptor type {ty:?} in storage class {storage}")
+            }
+        })
+    }
+
+    fn vertex_format(&self, id: u32) -> Result<vk::Format> {
+        let (component, count) = match self.ty(id)? {
+            Type::Vector { component, count } => (*component, *count),
+            _ => (id, 1),
+        };
+
+        let formats = match self.ty(component)? {
+            Type::Float { width: 32 } => [
+                vk::Format::R32_SFLOAT,
+                vk::Format::R32G32_SFLOAT,
+                vk::Format::R32G32B32_SFLOAT,
+                vk::Format::R32G32B32A32_SFLOAT,
+            ],
+            Type::Float { width: 16 } => [
+                vk::Format::R16_SFLOAT,
+                vk::Format::R16G16_SFLOAT,
+                vk::Format::R16G16B16_SFLOAT,
+                vk::Format::R16G16B16A16_SFLOAT,
+            ],
+            Type::Float { width: 64 } => [
+                vk::Format::R64_SFLOAT,
+                vk::Format::R64G64_SFLOAT,
+                vk::Format::R64G64B64_SFLOAT,
+                vk::Format::R64G64B64A64_SFLOAT,
+            ],
+            Type::Int {
+                width: 32,
+                signed: true,
+            } => [
+                vk::Format::R32_SINT,
+                vk::Format::R32G32_SINT,
+                vk::Format::R32G32B32_SINT,
+                vk::Format::R32G32B32A32_SINT,
+            ],
+            Type::Int {
+                width: 32,
+                signed: false,
+            } => [
+                vk::Format::R32_UINT,
+                vk::Format::R32G32_UINT,
+                vk::Format::R32G32B32_UINT,
+                vk::Format::R32G32B32A32_UINT,
+            ],
+            ty => bail!("unsupported vertex input type {ty:?}"),
+        };
+
+        if !(1..=4).contains(&count) {
+            bail!("unsupported vertex input vector size {count}");
+        }
+        Ok(formats[count as usize - 1])
+    }
+
+    /// matrices and arrays take up one location per column or element
+    fn vertex_inputs(&self, id: u32, location: u32, inputs: &mut Vec<VertexInput>) -> Result<u32> {
+        match self.ty(id)? {
+            Type::Matrix { column, count } => {
+                for i in 0..*count {
+                    inputs.push(VertexInput {
+                        location: location + i,
+                        format: self.vertex_format(*column)?,
+                    });
+                }
+                Ok(*count)
+            }
+            Type::Array { element, length } => {
+                let mut used = 0;
+                for _ in 0..self.array_length(*length)? {
+                    used += self.vertex_inputs(*element, location + used, inputs)?;
+                }
+                Ok(used)
+            }
+            _ => {
+                inputs.push(VertexInput {
+                    location,
+                    format: self.vertex_format(id)?,
+                });
+                Ok(1)
+            }
+        }
+    }
+}
+
+fn parse_module(words: &[u32]) -> Result<Module> {
+    let mut module = Module::default();
+
+    for inst in instructions(words)? {
+        let ops = inst.operands;
+        let op = |i: usize| {
+            ops.get(i)
+                .copied()
+                .with_context(|| format!("SPIR-V opcode {} is missing operands", inst.opcode))
+        };
+
+        match inst.opcode {
+            OP_ENTRY_POINT => {
+                let (name, len) = parse_string(ops.get(2..).unwrap_or_default())?;
+                module.entry_points.push((name, ops[2 + len..].to_vec()));
+            }
+            OP_TYPE_BOOL => {
+                module.types.insert(op(0)?, Type::Bool);
+            }
+            OP_TYPE_INT => {
+                let ty = Type::Int {
+                    width: op(1)?,
+                    signed: op(2)? == 1,
+                };
+                module.types.insert(op(0)?, ty);
+            }
+            OP_TYPE_FLOAT => {
+                module.types.insert(op(0)?, Type::Float { width: op(1)? });
+            }
+            OP_TYPE_VECTOR => {
+                let ty = Type::Vector {
+                    component: op(1)?,
+                    count: op(2)?,
+                };
+                module.types.insert(op(0)?, ty);
+            }
+            OP_TYPE_MATRIX => {
+                let ty = Type::Matrix {
+                    column: op(1)?,
+                    count: op(2)?,
+                };
+                module.types.insert(op(0)?, ty);
+            }
+            OP_TYPE_IMAGE => {
+                let ty = Type::Image {
+                    dim: op(2)?,
+                    sampled: op(6)?,
+                };
+                module.types.insert(op(0)?, ty);
+            }
+            OP_TYPE_SAMPLER => {
+                module.types.insert(op(0)?, Type::Sampler);
+            }
+            OP_TYPE_SAMPLED_IMAGE => {
+                module
+                    .types
+                    .insert(op(0)?, Type::SampledImage { image: op(1)? });
+            }
+            OP_TYPE_ARRAY => {
+                let ty = Type::Array {
+                    element: op(1)?,
+                    length: op(2)?,
+                };
+                module.types.insert(op(0)?, ty);
+            }
+            OP_TYPE_RUNTIME_ARRAY => {
+                module
+                    .types
+                    .insert(op(0)?, Type::RuntimeArray { element: op(1)? });
+            }
+            OP_TYPE_STRUCT => {
+                // checked first, an empty instruction would make the slice panic
+                let id = op(0)?;
+                let ty = Type::Struct {
+                    members: ops[1..].to_vec(),
+                };
+                module.types.insert(id, ty);
+            }
+            OP_TYPE_POINTER => {
+                module
+                    .types
+                    .insert(op(0)?, Type::Pointer { pointee: op(2)? });
+            }
+            OP_TYPE_ACCELERATION_STRUCTURE => {
+                module.types.insert(op(0)?, Type::AccelerationStructure);
+            }
+            OP_CONSTANT => {
+                // only the low word matters, array lengths always fit into 32 bits
+                module.constants.insert(op(1)?, op(2)?);
+            }
+            OP_VARIABLE => {
+                module.variables.push((op(1)?, op(0)?, op(2)?));
+            }
+            OP_DECORATE => {
+                let decorations = module.decorations.entry(op(0)?).or_default();
+                match op(1)? {
+                    DECORATION_DESCRIPTOR_SET => decorations.set = Some(op(2)?),
+                    DECORATION_BINDING => decorations.binding = Some(op(2)?),
+                    DECORATION_LOCATION => decorations.location = Some(op(2)?),
+                    DECORATION_BUILT_IN => decorations.built_in = true,
+                    DECORATION_BUFFER_BLOCK => decorations.buffer_block = true,
+                    DECORATION_ARRAY_STRIDE => decorations.array_stride = Some(op(2)?),
+                    _ => {}
+                }
+            }
+            OP_MEMBER_DECORATE => {
+                let decorations = module.members.entry((op(0)?, op(1)?)).or_default();
+                match op(2)? {
+                    DECORATION_OFFSET => decorations.offset = Some(op(3)?),
+                    DECORATION_MATRIX_STRIDE => decorations.matrix_stride = Some(op(3)?),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(module)
+}
+
+impl ShaderReflection {
+    /// reflects the resources used by `entry_point`
+    /// modules older than SPIR-V 1.4 don't list the resources of an entry point,
+    /// so all resources of the module are reported for them
+    pub fn new(code: &[u32], entry_point: &str) -> Result<Self> {
+        let module = parse_module(code)?;
+
+        let stages = super::spirv::entry_points(code)?;
+        let index = stages
+            .iter()
+            .position(|v| v.name == entry_point)
+            .with_context(|| format!("SPIR-V module has no entry point named {entry_point:?}"))?;
+
+        let stage = stages[index].stage;
+        let interface = &module.entry_points[index].1;
+        let filter_by_interface = code[1] >= VERSION_1_4;
+
+        let mut reflection = Self {
+            stage,
+            ..Default::default()
+        };
+
+        for &(id, pointer, storage) in &module.variables {
+            let is_interface = interface.contains(&id);
+            if filter_by_interface && storage != STORAGE_INPUT && !is_interface {
+                continue;
+            }
+
+            let Type::Pointer { pointee } = module.ty(pointer)? else {
+                bail!("variable %{id} doesn't have a pointer type");
+            };
+            let decorations = module.decorations(id);
+
+            match storage {
+                STORAGE_UNIFORM_CONSTANT | STORAGE_UNIFORM | STORAGE_STORAGE_BUFFER => {
+                    let (Some(set), Some(binding)) = (
+                        decorations.and_then(|v| v.set),
+                        decorations.and_then(|v| v.binding),
+                    ) else {
+                        continue;
+                    };
+
+                    let (element, count) = module.unwrap_arrays(*pointee)?;
+
+                    let bindings = reflection.descriptor_sets.entry(set).or_default();
+                    if bindings.iter().any(|v| v.binding == binding) {
+                        bail!("set {set} binding {binding} is declared more than once");
+                    }
+
+                    bindings.push(DescriptorType {
+                        ty: module.descriptor_type(element, storage)?,
+                        stage_flags: stage,
+                        count,
+                        binding,
+                    });
+                }
+                STORAGE_PUSH_CONSTANT => {
+                    let Type::Struct { members } = module.ty(*pointee)? else {
+                        bail!("push constant %{id} is not a struct");
+                    };
+
+                    let start = (0..members.len() as u32)
+                        .filter_map(|i| module.members.get(&(*pointee, i))?.offset)
+                        .min()
+                        .unwrap_or(0);
+                    let end = module.size_of(*pointee, None)?;
+
+                    reflection.push_constants = Some(vk::PushConstantRange {
+                        stage_flags: stage,
+                        offset: start,
+                        size: end - start,
+                    });
+                }
+                STORAGE_INPUT if stage == vk::ShaderStageFlags::VERTEX && is_interface => {
+                    let Some(decorations) = decorations.filter(|v| !v.built_in) else {
+                        continue;
+                    };
+                    let location = decorations
+                        .location
+                        .with_context(|| format!("vertex input %{id} has no location"))?;
+
+                    module.vertex_inputs(*pointee, location, &mut reflection.vertex_inputs)?;
+                }
+                _ => {}
+            }
+        }
+
+        for bindings in reflection.descriptor_sets.values_mut() {
+            bindings.sort_by_key(|v| v.binding);
+        }
+        reflection.vertex_inputs.sort_by_key(|v| v.location);
+
+        Ok(reflection)
+    }
+}
+
+impl PipelineReflection {
+    /// merges the reflection of every stage of a pipeline
+    /// fails if two stages disagree about the type or size of a binding
+    pub fn merge(stages: &[ShaderReflection]) -> Result<Self> {
+        let mut merged = Self::default();
+        let mut seen_stages = vk::ShaderStageFlags::empty();
+        let mut push_constants: Option<vk::PushConstantRange> = None;
+
+        for reflection in stages {
+            if seen_stages.intersects(reflection.stage) {
+                bail!("stage {:?} is reflected more than once", reflection.stage);
+            }
+            seen_stages |= reflection.stage;
+
+            for (set, bindings) in &reflection.descriptor_sets {
+                let merged_bindings = merged.descriptor_sets.entry(*set).or_default();
+
+                for binding in bindings {
+                    let Some(existing) = merged_bindings
+                        .iter_mut()
+                        .find(|v| v.binding == binding.binding)
+                    else {
+                        merged_bindings.push(*binding);
+                        continue;
+                    };
+
+                    if existing.ty != binding.ty || existing.count != binding.count {
+                        bail!(
+                            "set {set} binding {} is {:?}[{}] in {:?} but {:?}[{}] in {:?}",
+                            binding.binding,
+                            existing.ty,
+                            existing.count,
+                            existing.stage_flags,
+                            binding.ty,
+                            binding.count,
+                            binding.stage_flags,
+                        );
+                    }
+                    existing.stage_flags |= binding.stage_flags;
+                }
+
+                merged_bindings.sort_by_key(|v| v.binding);
+            }
+
+            if let Some(range) = reflection.push_constants {
+                push_constants = Some(match push_constants {
+                    None => range,
+                    Some(merged) => {
+                        let offset = merged.offset.min(range.offset);
+                        let end = (merged.offset + merged.size).max(range.offset + range.size);
+                        vk::PushConstantRange {
+                            stage_flags: merged.stage_flags | range.stage_flags,
+                            offset,
+                            size: end - offset,
+                        }
+                    }
+                });
+            }
+
+            if reflection.stage == vk::ShaderStageFlags::VERTEX {
+                merged.vertex_inputs = reflection.vertex_inputs.clone();
+            }
+        }
+
+        merged.push_constant_ranges = push_constants.into_iter().collect();
+
+        Ok(merged)
+    }
+
+    /// creates one layout for every set index up to the highest used one
+    /// unused set indices get an empty layout
+    /// fails for runtime sized arrays, use `create_bindless_set_layouts` for those
+    pub fn create_set_layouts(&self, device: Arc<Device>) -> Result<Vec<Arc<DescriptorSetLayout>>> {
+        self.set_bindings(None)?
+            .into_iter()
+            .map(|(bindings, flags)| {
+                DescriptorSetLayout::with_binding_flags(device.clone(), &bindings, &flags)
+            })
+            .collect()
+    }
+
+    /// like `create_set_layouts`, but runtime sized arrays get room for `runtime_array_count`
+    /// descriptors and don't have to be filled completely
+    /// needs `Device::supports_descriptor_indexing`
+    pub fn create_bindless_set_layouts(
+        &self,
+        device: Arc<Device>,
+        runtime_array_count: u32,
+    ) -> Result<Vec<Arc<DescriptorSetLayout>>> {
+        if !device.supports_descriptor_indexing() {
+            bail!("runtime sized descriptor arrays need descriptor indexing");
+        }
+
+        self.set_bindings(Some(runtime_array_count))?
+            .into_iter()
+            .map(|(bindings, flags)| {
+                DescriptorSetLayout::with_binding_flags(device.clone(), &bindings, &flags)
+            })
+            .collect()
+    }
+
+    /// the bindings and binding flags of every set index up to the highest used one
+    /// runtime sized arrays get `runtime_array_count` descriptors and are `PARTIALLY_BOUND`,
+    /// they aren't `VARIABLE_DESCRIPTOR_COUNT` because the allocators always use the full count
+    /// fails if there are runtime sized arrays but no count, sets without them get no flags
+    pub fn set_bindings(
+        &self,
+        runtime_array_count: Option<u32>,
+    ) -> Result<Vec<(Vec<DescriptorType>, Vec<vk::DescriptorBindingFlags>)>> {
+        let Some(&last) = self.descriptor_sets.keys().last() else {
+            return Ok(vec![]);
+        };
+
+        (0..=last)
+            .map(|set| {
+                let mut bindings = self.descriptor_sets.get(&set).cloned().unwrap_or_default();
+
+                let mut flags = vec![vk::DescriptorBindingFlags::empty(); bindings.len()];
+                for (binding, flags) in bindings.iter_mut().zip(&mut flags) {
+                    if binding.count != 0 {
+                        continue;
+                    }
+                    let Some(count) = runtime_array_count else {
+                        bail!(
+                            "binding {} of set {set} is a runtime sized array, it needs a count",
+                            binding.binding
+                        );
+                    };
+
+                    binding.count = count;
+                    *flags = vk::DescriptorBindingFlags::PARTIALLY_BOUND;
+                }
+
+                if flags.iter().all(|v| v.is_empty()) {
+                    flags.clear();
+                }
+                Ok((bindings, flags))
+            })
+            .collect()
+    }
+}
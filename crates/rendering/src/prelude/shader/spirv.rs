@@ -0,0 +1,146 @@
+use anyhow::{bail, Context, Result};
+use ash::vk;
+
+pub const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+const HEADER_WORDS: usize = 5;
+const OP_ENTRY_POINT: u16 = 15;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryPoint {
+    pub name: String,
+    pub stage: vk::ShaderStageFlags,
+}
+
+/// checks the header of a SPIR-V binary and converts it to native endian words
+/// the input doesn't need to be 4 byte aligned, the words are copied out
+pub fn spirv_words(bytes: &[u8]) -> Result<Vec<u32>> {
+    if !bytes.len().is_multiple_of(4) {
+        bail!(
+            "SPIR-V size must be a multiple of 4 bytes, got {} bytes",
+            bytes.len()
+        );
+    }
+    if bytes.len() < HEADER_WORDS * 4 {
+        bail!(
+            "SPIR-V binary is too small to contain a header ({} bytes)",
+            bytes.len()
+        );
+    }
+
+    let magic = [bytes[0], bytes[1], bytes[2], bytes[3]];
+
+    let from_bytes: fn([u8; 4]) -> u32 = if u32::from_le_bytes(magic) == SPIRV_MAGIC {
+        u32::from_le_bytes
+    } else if u32::from_be_bytes(magic) == SPIRV_MAGIC {
+        u32::from_be_bytes
+    } else {
+        bail!(
+            "invalid SPIR-V magic number {:#010x}",
+            u32::from_le_bytes(magic)
+        );
+    };
+
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|word| from_bytes([word[0], word[1], word[2], word[3]]))
+        .collect())
+}
+
+pub(crate) struct Instruction<'a> {
+    pub opcode: u16,
+    pub operands: &'a [u32],
+}
+
+/// splits the module (after the header) into instructions
+/// fails if a word count is zero or runs past the end of the module
+pub(crate) fn instructions(words: &[u32]) -> Result<Vec<Instruction<'_>>> {
+    if words.first() != Some(&SPIRV_MAGIC) || words.len() < HEADER_WORDS {
+        bail!("SPIR-V words don't start with a valid header");
+    }
+
+    let mut instructions = vec![];
+    let mut rest = &words[HEADER_WORDS..];
+
+    while let Some(&first) = rest.first() {
+        let word_count = (first >> 16) as usize;
+        let opcode = (first & 0xffff) as u16;
+
+        if word_count == 0 || word_count > rest.len() {
+            bail!(
+                "malformed SPIR-V instruction (opcode {opcode}, word count {word_count}) at word {}",
+                words.len() - rest.len()
+            );
+        }
+
+        instructions.push(Instruction {
+            opcode,
+            operands: &rest[1..word_count],
+        });
+        rest = &rest[word_count..];
+    }
+
+    Ok(instructions)
+}
+
+/// decodes a nul terminated literal string
+/// returns the string and the number of words it used
+pub(crate) fn parse_string(operands: &[u32]) -> Result<(String, usize)> {
+    let mut bytes = vec![];
+
+    for (i, word) in operands.iter().enumerate() {
+        for byte in word.to_le_bytes() {
+            if byte == 0 {
+                return Ok((String::from_utf8(bytes)?, i + 1));
+            }
+            bytes.push(byte);
+        }
+    }
+
+    bail!("unterminated SPIR-V literal string")
+}
+
+fn execution_model_stage(model: u32) -> Result<vk::ShaderStageFlags> {
+    Ok(match model {
+        0 => vk::ShaderStageFlags::VERTEX,
+        1 => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+        2 => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+        3 => vk::ShaderStageFlags::GEOMETRY,
+        4 => vk::ShaderStageFlags::FRAGMENT,
+        5 => vk::ShaderStageFlags::COMPUTE,
+        // the NV stages share their bits with the EXT ones
+        5267 => vk::ShaderStageFlags::TASK_NV,
+        5268 => vk::ShaderStageFlags::MESH_NV,
+        5313 => vk::ShaderStageFlags::RAYGEN_KHR,
+        5314 => vk::ShaderStageFlags::INTERSECTION_KHR,
+        5315 => vk::ShaderStageFlags::ANY_HIT_KHR,
+        5316 => vk::ShaderStageFlags::CLOSEST_HIT_KHR,
+        5317 => vk::ShaderStageFlags::MISS_KHR,
+        5318 => vk::ShaderStageFlags::CALLABLE_KHR,
+        5364 => vk::ShaderStageFlags::TASK_EXT,
+        5365 => vk::ShaderStageFlags::MESH_EXT,
+        _ => bail!("unsupported SPIR-V execution model {model}"),
+    })
+}
+
+pub fn entry_points(words: &[u32]) -> Result<Vec<EntryPoint>> {
+    instructions(words)?
+        .into_iter()
+        .filter(|inst| inst.opcode == OP_ENTRY_POINT)
+        .map(|inst| {
+            let model = *inst
+                .operands
+                .first()
+                .context("OpEntryPoint is missing operands")?;
+            let name = inst
+                .operands
+                .get(2..)
+                .context("OpEntryPoint is missing a name")?;
+
+            Ok(EntryPoint {
+                name: parse_string(name)?.0,
+                stage: execution_model_stage(model)?,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock, Weak},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+
+use crate::prelude::{Device, Fence, ShaderModule};
+
+struct WatchedShader {
+    modified: Option<SystemTime>,
+    module: Arc<ShaderModule>,
+}
+
+type BuildPipeline<P> = dyn Fn(&[Arc<ShaderModule>]) -> Result<Arc<P>> + Send + Sync;
+
+/// a pipeline that gets rebuilt by a `ShaderWatcher` when one of its shaders changes
+pub struct HotPipeline<P> {
+    shaders: Vec<PathBuf>,
+    build: Box<BuildPipeline<P>>,
+    current: RwLock<Arc<P>>,
+    pending: Mutex<Option<Arc<P>>>,
+}
+
+impl<P> HotPipeline<P> {
+    /// the latest pipeline that compiled successfully
+    pub fn get(&self) -> Arc<P> {
+        self.current.read().unwrap().clone()
+    }
+}
+
+trait Reload: Send + Sync {
+    fn uses(&self, path: &Path) -> bool;
+    fn rebuild(&self, modules: &HashMap<PathBuf, Arc<ShaderModule>>) -> Result<()>;
+    fn apply(&self);
+    /// drops a rebuilt pipeline that can't be swapped in
+    fn discard(&self);
+}
+
+impl<P: Send + Sync> Reload for HotPipeline<P> {
+    fn uses(&self, path: &Path) -> bool {
+        self.shaders.iter().any(|v| v == path)
+    }
+
+    fn rebuild(&self, modules: &HashMap<PathBuf, Arc<ShaderModule>>) -> Result<()> {
+        let modules: Vec<_> = self
+            .shaders
+            .iter()
+            .map(|path| modules[path].clone())
+            .collect();
+
+        let pipeline = (self.build)(&modules);
+        // a failed build must not leave an older rebuild behind
+        *self.pending.lock().unwrap() = pipeline.as_ref().ok().cloned();
+        pipeline.map(|_| ())
+    }
+
+    fn apply(&self) {
+        if let Some(pipeline) = self.pending.lock().unwrap().take() {
+            *self.current.write().unwrap() = pipeline;
+        }
+    }
+
+    fn discard(&self) {
+        self.pending.lock().unwrap().take();
+    }
+}
+
+#[derive(Default)]
+pub struct ReloadReport {
+    pub reloaded_shaders: Vec<PathBuf>,
+    pub rebuilt_pipelines: usize,
+    /// shaders or pipelines that failed, the previous version is kept for them
+    pub errors: Vec<anyhow::Error>,
+}
+
+/// watches SPIR-V files on disk and rebuilds the pipelines using them
+/// call `reload_changed` once per frame, e.g. right before recording
+pub struct ShaderWatcher {
+    device: Arc<Device>,
+    shaders: Mutex<HashMap<PathBuf, WatchedShader>>,
+    pipelines: Mutex<Vec<Weak<dyn Reload>>>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|v| v.modified()).ok()
+}
+
+impl ShaderWatcher {
+    pub fn new(device: Arc<Device>) -> Arc<Self> {
+        Self {
+            device,
+            shaders: Mutex::default(),
+            pipelines: Mutex::default(),
+        }
+        .into()
+    }
+
+    /// loads a shader and starts watching it
+    /// loading the same path twice returns the already loaded module
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<Arc<ShaderModule>> {
+        let path = path.as_ref().to_path_buf();
+        let mut shaders = self.shaders.lock().unwrap();
+
+        if let Some(shader) = shaders.get(&path) {
+            return Ok(shader.module.clone());
+        }
+
+        let modified = modified(&path);
+        let module = ShaderModule::from_file(self.device.clone(), &path)?;
+
+        shaders.insert(
+            path,
+            WatchedShader {
+                modified,
+                module: module.clone(),
+            },
+        );
+
+        Ok(module)
+    }
+
+    /// builds a pipeline from the shaders at `paths`
+    /// `build` gets the modules in the same order as `paths` and is called again on every reload
+    pub fn pipeline<P, F>(
+        &self,
+        paths: &[impl AsRef<Path>],
+        build: F,
+    ) -> Result<Arc<HotPipeline<P>>>
+    where
+        P: Send + Sync + 'static,
+        F: Fn(&[Arc<ShaderModule>]) -> Result<Arc<P>> + Send + Sync + 'static,
+    {
+        let modules = paths
+            .iter()
+            .map(|path| self.load(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        let pipeline = Arc::new(HotPipeline {
+            shaders: paths.iter().map(|v| v.as_ref().to_path_buf()).collect(),
+            current: RwLock::new(build(&modules)?),
+            build: Box::new(build),
+            pending: Mutex::default(),
+        });
+
+        let weak: Weak<dyn Reload> = Arc::downgrade(&pipeline) as _;
+        self.pipelines.lock().unwrap().push(weak);
+
+        Ok(pipeline)
+    }
+
+    /// reloads every shader whose file changed and rebuilds the pipelines that use it
+    /// the new pipelines are swapped in after all `in_flight` fences are signaled,
+    /// so the GPU is never using a pipeline that gets dropped
+    pub fn reload_changed(&self, in_flight: &[&Fence]) -> ReloadReport {
+        let mut report = ReloadReport::default();
+
+        // the locks are only held for the bookkeeping, not while the user code builds pipelines
+        let modules: HashMap<_, _> = {
+            let mut shaders = self.shaders.lock().unwrap();
+
+            for (path, shader) in shaders.iter_mut() {
+                let modified = modified(path);
+                if modified == shader.modified {
+                    continue;
+                }
+                // a broken file is only retried once it changes again
+                shader.modified = modified;
+
+                match ShaderModule::from_file(self.device.clone(), path) {
+                    Ok(module) => {
+                        shader.module = module;
+                        report.reloaded_shaders.push(path.clone());
+                    }
+                    Err(err) => report.errors.push(err),
+                }
+            }
+
+            shaders
+                .iter()
+                .map(|(path, shader)| (path.clone(), shader.module.clone()))
+                .collect()
+        };
+
+        if report.reloaded_shaders.is_empty() {
+            return report;
+        }
+
+        let affected: Vec<_> = {
+            let mut pipelines = self.pipelines.lock().unwrap();
+            pipelines.retain(|v| v.strong_count() > 0);
+            pipelines
+                .iter()
+                .filter_map(Weak::upgrade)
+                .filter(|pipeline| report.reloaded_shaders.iter().any(|v| pipeline.uses(v)))
+                .collect()
+        };
+
+        let rebuilt: Vec<_> = affected
+            .into_iter()
+            .filter(|pipeline| match pipeline.rebuild(&modules) {
+                Ok(()) => true,
+                Err(err) => {
+                    report
+                        .errors
+                        .push(err.context("failed to rebuild pipeline"));
+                    false
+                }
+            })
+            .collect();
+
+        if rebuilt.is_empty() {
+            return report;
+        }
+
+        for fence in in_flight {
+            if let Err(err) = fence
+                .wait_for_finished()
+                .context("failed to wait for frames in flight")
+            {
+                report.errors.push(err);
+                for pipeline in &rebuilt {
+                    pipeline.discard();
+                }
+                return report;
+            }
+        }
+
+        for pipeline in &rebuilt {
+            pipeline.apply();
+        }
+        report.rebuilt_pipelines = rebuilt.len();
+
+        report
+    }
+}
@@ -0,0 +1,234 @@
+use anyhow::{bail, Result};
+use ash::vk;
+
+/// everything a surface supports on a physical device
+#[derive(Clone, Debug)]
+pub struct SurfaceSupport {
+    pub capabilities: vk::SurfaceCapabilitiesKHR,
+    pub formats: Vec<vk::SurfaceFormatKHR>,
+    pub present_modes: Vec<vk::PresentModeKHR>,
+}
+
+impl SurfaceSupport {
+    pub fn supports_format(&self, format: vk::SurfaceFormatKHR) -> bool {
+        self.formats.contains(&format)
+    }
+
+    pub fn supports_present_mode(&self, mode: vk::PresentModeKHR) -> bool {
+        self.present_modes.contains(&mode)
+    }
+}
+
+/// the settings a swapchain gets created with, picked from a `SwapchainConfig`
+#[derive(Clone, Debug)]
+pub struct SurfaceInfos {
+    pub capabilities: vk::SurfaceCapabilitiesKHR,
+    pub present_mode: vk::PresentModeKHR,
+    pub format: vk::SurfaceFormatKHR,
+    pub image_count: u32,
+    pub composite_alpha: vk::CompositeAlphaFlagsKHR,
+    pub usage: vk::ImageUsageFlags,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VsyncMode {
+    /// waits for vertical blank, always supported
+    Fifo,
+    /// waits for vertical blank, but replaces the queued image instead of blocking
+    Mailbox,
+    /// doesn't wait, can tear
+    Immediate,
+    /// waits for vertical blank, unless the image is late, then it can tear
+    FifoRelaxed,
+}
+
+impl VsyncMode {
+    pub fn present_mode(&self) -> vk::PresentModeKHR {
+        match self {
+            Self::Fifo => vk::PresentModeKHR::FIFO,
+            Self::Mailbox => vk::PresentModeKHR::MAILBOX,
+            Self::Immediate => vk::PresentModeKHR::IMMEDIATE,
+            Self::FifoRelaxed => vk::PresentModeKHR::FIFO_RELAXED,
+        }
+    }
+
+    /// the modes to try in order, ends with fifo because every surface supports it
+    /// modes that don't tear only fall back to modes that don't tear either
+    pub fn fallbacks(&self) -> &'static [Self] {
+        match self {
+            Self::Fifo => &[Self::Fifo],
+            Self::Mailbox => &[Self::Mailbox, Self::Fifo],
+            Self::Immediate => &[Self::Immediate, Self::Mailbox, Self::Fifo],
+            Self::FifoRelaxed => &[Self::FifoRelaxed, Self::Fifo],
+        }
+    }
+}
+
+/// what the swapchain should look like, the surface picks the closest supported settings
+#[derive(Clone, Debug)]
+pub struct SwapchainConfig {
+    /// ranked, the first supported one is used
+    pub formats: Vec<vk::SurfaceFormatKHR>,
+    pub vsync: VsyncMode,
+    /// `None` uses one more than the minimum
+    pub image_count: Option<u32>,
+    pub composite_alpha: vk::CompositeAlphaFlagsKHR,
+    /// `COLOR_ATTACHMENT` is always added
+    pub usage: vk::ImageUsageFlags,
+}
+
+fn surface_format(format: vk::Format, color_space: vk::ColorSpaceKHR) -> vk::SurfaceFormatKHR {
+    vk::SurfaceFormatKHR {
+        format,
+        color_space,
+    }
+}
+
+impl Default for SwapchainConfig {
+    fn default() -> Self {
+        Self {
+            formats: Self::srgb_formats(),
+            vsync: VsyncMode::Fifo,
+            image_count: None,
+            composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
+            usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+        }
+    }
+}
+
+impl SwapchainConfig {
+    /// 8 bit formats that do the srgb conversion on write
+    pub fn srgb_formats() -> Vec<vk::SurfaceFormatKHR> {
+        [vk::Format::B8G8R8A8_SRGB, vk::Format::R8G8B8A8_SRGB]
+            .into_iter()
+            .map(|v| surface_format(v, vk::ColorSpaceKHR::SRGB_NONLINEAR))
+            .collect()
+    }
+
+    /// hdr10 and scrgb, followed by the srgb formats
+    /// the instance needs `VK_EXT_swapchain_colorspace` for surfaces to report them
+    pub fn hdr_formats() -> Vec<vk::SurfaceFormatKHR> {
+        let mut formats = vec![
+            surface_format(
+                vk::Format::A2B10G10R10_UNORM_PACK32,
+                vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+            ),
+            surface_format(
+                vk::Format::R16G16B16A16_SFLOAT,
+                vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+            ),
+        ];
+        formats.extend(Self::srgb_formats());
+        formats
+    }
+
+    pub fn formats(mut self, formats: Vec<vk::SurfaceFormatKHR>) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    pub fn vsync(mut self, vsync: VsyncMode) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    pub fn image_count(mut self, count: u32) -> Self {
+        self.image_count = Some(count);
+        self
+    }
+
+    pub fn composite_alpha(mut self, alpha: vk::CompositeAlphaFlagsKHR) -> Self {
+        self.composite_alpha = alpha;
+        self
+    }
+
+    pub fn usage(mut self, usage: vk::ImageUsageFlags) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// the first preferred format the surface supports, otherwise the first one it has
+    pub fn choose_format(&self, support: &SurfaceSupport) -> Result<vk::SurfaceFormatKHR> {
+        // a single undefined entry means the surface takes any format
+        if let [only] = support.formats[..] {
+            if only.format == vk::Format::UNDEFINED {
+                if let Some(preferred) = self.formats.first() {
+                    return Ok(*preferred);
+                }
+            }
+        }
+
+        if let Some(format) = self.formats.iter().find(|v| support.supports_format(**v)) {
+            return Ok(*format);
+        }
+        match support.formats.first() {
+            Some(format) => Ok(*format),
+            None => bail!("the surface has no formats"),
+        }
+    }
+
+    pub fn choose_present_mode(&self, support: &SurfaceSupport) -> vk::PresentModeKHR {
+        self.vsync
+            .fallbacks()
+            .iter()
+            .map(|v| v.present_mode())
+            .find(|v| support.supports_present_mode(*v))
+            .unwrap_or(vk::PresentModeKHR::FIFO)
+    }
+
+    /// clamped to what the surface allows, a maximum of 0 means no limit
+    pub fn choose_image_count(&self, capabilities: &vk::SurfaceCapabilitiesKHR) -> u32 {
+        let count = self
+            .image_count
+            .unwrap_or(capabilities.min_image_count + 1)
+            .max(capabilities.min_image_count);
+        if capabilities.max_image_count > 0 {
+            count.min(capabilities.max_image_count)
+        } else {
+            count
+        }
+    }
+
+    /// the preferred mode if supported, otherwise the first supported one
+    pub fn choose_composite_alpha(
+        &self,
+        capabilities: &vk::SurfaceCapabilitiesKHR,
+    ) -> vk::CompositeAlphaFlagsKHR {
+        let supported = capabilities.supported_composite_alpha;
+        if supported.contains(self.composite_alpha) {
+            return self.composite_alpha;
+        }
+
+        [
+            vk::CompositeAlphaFlagsKHR::OPAQUE,
+            vk::CompositeAlphaFlagsKHR::INHERIT,
+            vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+            vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+        ]
+        .into_iter()
+        .find(|v| supported.contains(*v))
+        .unwrap_or(vk::CompositeAlphaFlagsKHR::OPAQUE)
+    }
+
+    /// picks the settings for a swapchain, fails if the usage isn't supported
+    pub fn resolve(&self, support: &SurfaceSupport) -> Result<SurfaceInfos> {
+        let capabilities = support.capabilities;
+
+        let usage = self.usage | vk::ImageUsageFlags::COLOR_ATTACHMENT;
+        if !capabilities.supported_usage_flags.contains(usage) {
+            bail!(
+                "the surface doesn't support the usage {usage:?}, only {:?}",
+                capabilities.supported_usage_flags
+            );
+        }
+
+        Ok(SurfaceInfos {
+            capabilities,
+            present_mode: self.choose_present_mode(support),
+            format: self.choose_format(support)?,
+            image_count: self.choose_image_count(&capabilities),
+            composite_alpha: self.choose_composite_alpha(&capabilities),
+            usage,
+        })
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use super::SwapchainFramebuffers;
+use crate::prelude::{
+    Device, Fence, Framebuffer, Image, ImageView, Queue, RenderPass, RenderTarget, RgbaImage,
+    Semaphore, SwapchainStatus,
+};
+
+/// offscreen images that frames can be acquired from and presented to like a swapchain,
+/// for rendering without a window, presenting only marks the image as finished
+pub struct HeadlessTarget {
+    device: Arc<Device>,
+    images: Vec<Arc<Image>>,
+    views: Vec<Arc<ImageView>>,
+    raw_images: Vec<vk::Image>,
+    // signaled once the last present of the image with the same index finished
+    presented: Vec<Arc<Fence>>,
+    next: AtomicU32,
+    last_presented: Mutex<Option<u32>>,
+    extent: vk::Extent2D,
+    format: vk::Format,
+    framebuffers: RwLock<Option<SwapchainFramebuffers>>,
+}
+
+impl HeadlessTarget {
+    /// the images can be rendered to and read back, `format` has to support color attachments
+    pub fn new(
+        device: Arc<Device>,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        image_count: u32,
+    ) -> Result<Arc<Self>> {
+        if extent.width == 0 || extent.height == 0 {
+            bail!("headless target needs a size, got {extent:?}");
+        }
+
+        let mut images = vec![];
+        let mut views = vec![];
+        let mut presented = vec![];
+        for _ in 0..image_count.max(1) {
+            let info = vk::ImageCreateInfo::default()
+                .image_type(vk::ImageType::TYPE_2D)
+                .format(format)
+                .extent(extent.into())
+                .mip_levels(1)
+                .array_layers(1)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(
+                    vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+                )
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED);
+            let image = Image::new(device.clone(), info)?;
+
+            let view_info = vk::ImageViewCreateInfo::default()
+                .image(*image.as_raw())
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(format)
+                .subresource_range(image.subresource_range());
+            views.push(ImageView::new(device.clone(), image.clone(), view_info)?);
+            images.push(image);
+            presented.push(Fence::new(device.clone())?);
+        }
+
+        Ok(Self {
+            raw_images: images.iter().map(|v| *v.as_raw()).collect(),
+            device,
+            images,
+            views,
+            presented,
+            next: AtomicU32::new(0),
+            last_presented: Mutex::new(None),
+            extent,
+            format,
+            framebuffers: RwLock::new(None),
+        }
+        .into())
+    }
+
+    /// creates a framebuffer for every image, like `Swapchain::set_render_pass`
+    pub fn set_render_pass(&self, render_pass: Arc<RenderPass>) -> Result<()> {
+        let framebuffers =
+            SwapchainFramebuffers::new(&self.device, render_pass, &self.views, self.extent)?;
+        *self.framebuffers.write().unwrap() = Some(framebuffers);
+        Ok(())
+    }
+
+    pub fn image(&self, index: u32) -> Option<&Arc<Image>> {
+        self.images.get(index as usize)
+    }
+
+    pub fn view(&self, index: u32) -> Option<&Arc<ImageView>> {
+        self.views.get(index as usize)
+    }
+
+    /// the index of the image that was presented most recently
+    pub fn last_presented(&self) -> Option<u32> {
+        *self.last_presented.lock().unwrap()
+    }
+
+    /// waits until the image at `index` was presented and copies it to the host
+    pub fn read_image(&self, index: u32) -> Result<RgbaImage> {
+        let Some(image) = self.images.get(index as usize) else {
+            bail!("headless target has no image {index}");
+        };
+        self.presented[index as usize].wait_for_finished()?;
+        self.device.download_image(image)
+    }
+
+    /// reads back the most recently presented image and writes it to `path`
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        let Some(index) = self.last_presented() else {
+            bail!("nothing was presented to the headless target yet");
+        };
+        self.read_image(index)?.save_png(path)
+    }
+}
+
+impl RenderTarget for HeadlessTarget {
+    fn acquire_image(&self, semaphore: &Semaphore) -> Result<Option<(u32, SwapchainStatus)>> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.images.len() as u32;
+
+        // the image can't be handed out again before its last frame finished
+        self.presented[index as usize].wait_for_finished()?;
+
+        // there is no presentation engine to signal the semaphore, an empty submission does it
+        let semaphores = [*semaphore.as_raw()];
+        let info = vk::SubmitInfo::default().signal_semaphores(&semaphores);
+        self.device
+            .graphics_queue()
+            .submit(&[info], vk::Fence::null())?;
+
+        Ok(Some((index, SwapchainStatus::Optimal)))
+    }
+
+    fn present_image(
+        &self,
+        index: u32,
+        queue: &Queue,
+        wait: &Semaphore,
+    ) -> Result<SwapchainStatus> {
+        let Some(image) = self.images.get(index as usize) else {
+            bail!("headless target has no image {index}");
+        };
+        let fence = &self.presented[index as usize];
+
+        // waiting consumes the semaphore, the fence tells when the frame is finished
+        let semaphores = [*wait.as_raw()];
+        let stages = [vk::PipelineStageFlags::ALL_COMMANDS];
+        let info = vk::SubmitInfo::default()
+            .wait_semaphores(&semaphores)
+            .wait_dst_stage_mask(&stages);
+
+        fence.begin_submit()?;
+        if let Err(err) = queue.submit(&[info], *fence.as_raw()) {
+            fence.cancel_submit();
+            return Err(err);
+        }
+
+        image.set_layout(self.present_layout());
+        *self.last_presented.lock().unwrap() = Some(index);
+        Ok(SwapchainStatus::Optimal)
+    }
+
+    fn images(&self) -> &[vk::Image] {
+        &self.raw_images
+    }
+
+    fn image_views(&self) -> &[Arc<ImageView>] {
+        &self.views
+    }
+
+    fn framebuffer(&self, index: u32) -> Option<Arc<Framebuffer>> {
+        self.framebuffers
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|v| v.framebuffers.get(index as usize).cloned())
+    }
+
+    fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    fn generation(&self) -> u64 {
+        0
+    }
+
+    /// ready to be copied, `PRESENT_SRC_KHR` would need the swapchain extension
+    fn present_layout(&self) -> vk::ImageLayout {
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+    }
+
+    /// the images don't depend on a window, so the target stays as it is
+    fn recreate_target(self: Arc<Self>) -> Result<Option<Arc<dyn RenderTarget>>> {
+        Ok(Some(self))
+    }
+}
+
+impl Drop for HeadlessTarget {
+    fn drop(&mut self) {
+        // the images must not go away while a frame still renders to them
+        for fence in &self.presented {
+            fence.wait_for_finished().ok();
+        }
+    }
+}
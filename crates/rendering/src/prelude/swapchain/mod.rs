@@ -1,34 +1,137 @@
+mod config;
+mod headless;
 mod surface;
-pub use surface::Surface;
+mod target;
+pub use config::*;
+pub use headless::*;
+pub use surface::{choose_extent, Surface};
+pub use target::*;
 
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
-use crate::prelude::Device;
-use anyhow::Result;
+use crate::prelude::{Device, Framebuffer, ImageView, Queue, RenderPass, Semaphore};
+use anyhow::{Context, Result};
 use ash::vk;
 
+/// what happened to the swapchain while acquiring or presenting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapchainStatus {
+    Optimal,
+    /// still usable, but it should be recreated soon
+    Suboptimal,
+    /// can't be used anymore, it has to be recreated
+    OutOfDate,
+}
+
+impl SwapchainStatus {
+    pub fn needs_recreation(&self) -> bool {
+        *self != Self::Optimal
+    }
+}
+
 pub struct Swapchain {
-    handle: vk::SwapchainKHR,
-    loader: ash::khr::swapchain::Device,
+    handle: Arc<SwapchainHandle>,
     device: Arc<Device>,
     images: Vec<vk::Image>,
+    // one per image, with the same index
+    views: Vec<Arc<ImageView>>,
+    extent: vk::Extent2D,
     surface: Arc<Surface>,
-    present_semaphore: vk::Semaphore,
+    generation: u64,
+    framebuffers: RwLock<Option<SwapchainFramebuffers>>,
+    config: SwapchainConfig,
+    format: vk::SurfaceFormatKHR,
+    present_mode: vk::PresentModeKHR,
+}
+
+/// owns the swapchain and with it the images, the views keep it alive
+struct SwapchainHandle {
+    handle: vk::SwapchainKHR,
+    loader: ash::khr::swapchain::Device,
+}
+
+impl Drop for SwapchainHandle {
+    fn drop(&mut self) {
+        unsafe { self.loader.destroy_swapchain(self.handle, None) };
+    }
+}
+
+/// framebuffers for every swapchain image, they move over to the recreated swapchain
+struct SwapchainFramebuffers {
+    render_pass: Arc<RenderPass>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+}
+
+impl SwapchainFramebuffers {
+    fn new(
+        device: &Arc<Device>,
+        render_pass: Arc<RenderPass>,
+        views: &[Arc<ImageView>],
+        extent: vk::Extent2D,
+    ) -> Result<Self> {
+        let framebuffers = views
+            .iter()
+            .map(|view| {
+                Framebuffer::new(
+                    device.clone(),
+                    render_pass.clone(),
+                    std::slice::from_ref(view),
+                    extent,
+                )
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            render_pass,
+            framebuffers,
+        })
+    }
 }
 
 impl Swapchain {
+    /// fails if the window has no size, for example because it is minimized
     pub fn new(device: Arc<Device>, surface: Arc<Surface>) -> Result<Arc<Self>> {
-        let infos = surface.setup_infos(device.clone()).unwrap();
+        Self::with_config(device, surface, SwapchainConfig::default())
+    }
+
+    /// like `new`, with the format, present mode and image settings picked from `config`
+    pub fn with_config(
+        device: Arc<Device>,
+        surface: Arc<Surface>,
+        config: SwapchainConfig,
+    ) -> Result<Arc<Self>> {
+        Self::create(device, surface, config, None)?
+            .context("can't create a swapchain for a window without size")
+    }
+
+    /// creates a swapchain that replaces this one, with the current size of the window
+    /// returns `None` if the window has no size, then no frames should be rendered
+    /// this swapchain must not be used for acquiring anymore and
+    /// has to be dropped once its images aren't used anymore
+    pub fn recreate(&self) -> Result<Option<Arc<Self>>> {
+        Self::create(
+            self.device.clone(),
+            self.surface.clone(),
+            self.config.clone(),
+            Some(self),
+        )
+    }
+
+    fn create(
+        device: Arc<Device>,
+        surface: Arc<Surface>,
+        config: SwapchainConfig,
+        old: Option<&Swapchain>,
+    ) -> Result<Option<Arc<Self>>> {
+        let infos = surface.setup_infos(device.clone(), &config)?;
 
         let surface_capabilities = infos.capabilities;
         let present_mode = infos.present_mode;
         let surface_format = infos.format;
 
-        let mut desired_image_count = surface_capabilities.min_image_count + 1;
-        if surface_capabilities.max_image_count > 0
-            && desired_image_count > surface_capabilities.max_image_count
-        {
-            desired_image_count = surface_capabilities.max_image_count;
+        let extent = choose_extent(&surface_capabilities, surface.size());
+        if extent.width == 0 || extent.height == 0 {
+            return Ok(None);
         }
 
         let pre_transform = if surface_capabilities
@@ -42,84 +145,192 @@ impl Swapchain {
 
         let swapchain_create_info = vk::SwapchainCreateInfoKHR::default()
             .surface(*surface.as_raw())
-            .min_image_count(desired_image_count)
+            .min_image_count(infos.image_count)
             .image_color_space(surface_format.color_space)
             .image_format(surface_format.format)
-            .image_extent(surface.size())
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+            .image_extent(extent)
+            .image_usage(infos.usage)
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
             .pre_transform(pre_transform)
-            .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+            .composite_alpha(infos.composite_alpha)
             .present_mode(present_mode)
             .clipped(true)
-            .image_array_layers(1);
+            .image_array_layers(1)
+            .old_swapchain(old.map_or(vk::SwapchainKHR::null(), |v| v.handle.handle));
 
         let swapchain_loader =
             ash::khr::swapchain::Device::new(&device.instance().as_raw(), &device.as_raw());
 
-        let swapchain =
-            unsafe { swapchain_loader.create_swapchain(&swapchain_create_info, None) }.unwrap();
-
-        let images = unsafe { swapchain_loader.get_swapchain_images(swapchain) }?;
+        let swapchain = unsafe { swapchain_loader.create_swapchain(&swapchain_create_info, None) }?;
 
-        let present_semaphore =
-            unsafe { device.as_raw().create_semaphore(&Default::default(), None) }?;
-
-        Ok(Self {
+        // from here on drop cleans up whatever was created already
+        let handle = Arc::new(SwapchainHandle {
             handle: swapchain,
             loader: swapchain_loader,
+        });
+        let images = unsafe { handle.loader.get_swapchain_images(swapchain) }?;
+
+        let mut new = Self {
+            handle,
             device,
             images,
+            views: vec![],
+            extent,
             surface,
-            present_semaphore,
+            generation: old.map_or(0, |v| v.generation + 1),
+            framebuffers: RwLock::new(None),
+            config,
+            format: surface_format,
+            present_mode,
+        };
+
+        for image in new.images.clone() {
+            let info = vk::ImageViewCreateInfo::default()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(surface_format.format)
+                .subresource_range(
+                    vk::ImageSubresourceRange::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .level_count(1)
+                        .layer_count(1),
+                );
+            let view = ImageView::for_image_of(
+                new.device.clone(),
+                new.handle.clone(),
+                infos.usage,
+                info,
+            )?;
+            new.views.push(view);
+        }
+
+        let render_pass = old.and_then(|v| v.render_pass());
+        if let Some(render_pass) = render_pass {
+            new.set_render_pass(render_pass)?;
         }
-        .into())
+
+        Ok(Some(new.into()))
+    }
+
+    /// creates a framebuffer for every image, replacing the ones of an earlier render pass
+    /// the render pass needs a single color attachment with the swapchain format
+    /// recreated swapchains build their framebuffers for the same render pass
+    pub fn set_render_pass(&self, render_pass: Arc<RenderPass>) -> Result<()> {
+        let framebuffers =
+            SwapchainFramebuffers::new(&self.device, render_pass, &self.views, self.extent)?;
+        *self.framebuffers.write().unwrap() = Some(framebuffers);
+        Ok(())
     }
 
-    pub fn aquire_next_image(&self) -> (u32, bool) {
-        unsafe {
-            self.loader.acquire_next_image(
-                self.handle,
+    pub fn render_pass(&self) -> Option<Arc<RenderPass>> {
+        self.framebuffers
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|v| v.render_pass.clone())
+    }
+
+    /// the framebuffer of the image at `index`, if a render pass was set
+    pub fn framebuffer(&self, index: u32) -> Option<Arc<Framebuffer>> {
+        self.framebuffers
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|v| v.framebuffers.get(index as usize).cloned())
+    }
+
+    /// acquires the next image, `semaphore` is signaled once it can be rendered to
+    /// returns `None` if the swapchain is out of date, then `semaphore` isn't signaled
+    pub fn acquire_image(&self, semaphore: &Semaphore) -> Result<Option<(u32, SwapchainStatus)>> {
+        let result = unsafe {
+            self.handle.loader.acquire_next_image(
+                self.handle.handle,
                 u64::MAX,
-                self.present_semaphore,
+                *semaphore.as_raw(),
                 vk::Fence::null(),
             )
+        };
+        match result {
+            Ok((index, false)) => Ok(Some((index, SwapchainStatus::Optimal))),
+            Ok((index, true)) => Ok(Some((index, SwapchainStatus::Suboptimal))),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(None),
+            Err(err) => Err(err.into()),
         }
-        .unwrap()
     }
 
-    pub fn present(&self, index: u32, queue: vk::Queue) {
-        let semaphores = [self.present_semaphore];
-        let swapchains = [self.handle];
+    /// presents the image at `index` once `wait` is signaled
+    pub fn present_image(
+        &self,
+        index: u32,
+        queue: &Queue,
+        wait: &Semaphore,
+    ) -> Result<SwapchainStatus> {
+        let semaphores = [*wait.as_raw()];
+        let swapchains = [self.handle.handle];
         let image_indexes = [index];
 
         let present_info = vk::PresentInfoKHR::default()
-            .wait_semaphores(&semaphores) // &base.rendering_complete_semaphore)
+            .wait_semaphores(&semaphores)
             .swapchains(&swapchains)
             .image_indices(&image_indexes);
-        unsafe { self.loader.queue_present(queue, &present_info) }.unwrap();
+        let result =
+            queue.with_lock(|queue| unsafe { self.handle.loader.queue_present(queue, &present_info) });
+        match result {
+            Ok(false) => Ok(SwapchainStatus::Optimal),
+            Ok(true) => Ok(SwapchainStatus::Suboptimal),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(SwapchainStatus::OutOfDate),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn images(&self) -> &[vk::Image] {
+        &self.images
+    }
+
+    /// a 2d color view of the image at `index`, it keeps the swapchain images alive
+    pub fn image_view(&self, index: u32) -> Option<&Arc<ImageView>> {
+        self.views.get(index as usize)
+    }
+
+    pub fn image_views(&self) -> &[Arc<ImageView>] {
+        &self.views
+    }
+
+    /// the size the swapchain images were created with
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// counts the recreations, objects that depend on the images
+    /// have to be rebuilt when it changes
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn surface(&self) -> &Arc<Surface> {
+        &self.surface
     }
 
     pub fn as_raw(&self) -> &vk::SwapchainKHR {
-        &self.handle
+        &self.handle.handle
     }
 
     pub fn resolution(&self) -> vk::Extent2D {
-        self.surface.size()
+        self.extent
     }
     pub fn format(&self) -> vk::Format {
-        self.surface.infos().format.format
+        self.format.format
     }
-}
 
-impl Drop for Swapchain {
-    fn drop(&mut self) {
-        unsafe {
-            self.device
-                .as_raw()
-                .destroy_semaphore(self.present_semaphore, None);
+    pub fn color_space(&self) -> vk::ColorSpaceKHR {
+        self.format.color_space
+    }
 
-            self.loader.destroy_swapchain(self.handle, None);
-        }
+    pub fn present_mode(&self) -> vk::PresentModeKHR {
+        self.present_mode
+    }
+
+    pub fn config(&self) -> &SwapchainConfig {
+        &self.config
     }
 }
@@ -2,7 +2,7 @@ use anyhow::Result;
 use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
 use std::sync::{Arc, RwLock};
 
-use crate::prelude::{Device, Instance};
+use crate::prelude::{Device, Instance, SurfaceInfos, SurfaceSupport, SwapchainConfig};
 use ash::vk;
 use winit::window::Window;
 
@@ -15,13 +15,6 @@ pub struct Surface {
     window: Arc<Window>,
 }
 
-#[derive(Clone)]
-pub struct SurfaceInfos {
-    pub capabilities: vk::SurfaceCapabilitiesKHR,
-    pub present_mode: vk::PresentModeKHR,
-    pub format: vk::SurfaceFormatKHR,
-}
-
 impl Surface {
     pub fn new(instance: Arc<Instance>, window: Arc<Window>) -> Result<Arc<Self>> {
         let handle = unsafe {
@@ -53,6 +46,8 @@ impl Surface {
         self.window.clone()
     }
 
+    /// the inner size of the window, swapchains use `choose_extent` with it,
+    /// because the surface can require a different size
     pub fn size(&self) -> vk::Extent2D {
         let size = self.window.inner_size();
         vk::Extent2D {
@@ -61,27 +56,52 @@ impl Surface {
         }
     }
 
-    pub(crate) fn setup_infos(&self, device: Arc<Device>) -> Result<SurfaceInfos> {
+    /// true if queues of `queue_family` on `physical_device` can present to the surface
+    pub fn supports_present(
+        &self,
+        physical_device: vk::PhysicalDevice,
+        queue_family: u32,
+    ) -> Result<bool> {
+        let supported = unsafe {
+            self.loader.get_physical_device_surface_support(
+                physical_device,
+                queue_family,
+                self.handle,
+            )
+        }?;
+        Ok(supported)
+    }
+
+    /// the capabilities, formats and present modes of the surface on the device
+    pub fn support(&self, device: &Device) -> Result<SurfaceSupport> {
         let capabilities = unsafe {
             self.loader
                 .get_physical_device_surface_capabilities(*device.physical(), *self.as_raw())?
         };
 
-        let present_mode = unsafe {
+        let present_modes = unsafe {
             self.loader
-                .get_physical_device_surface_present_modes(*device.physical(), *self.as_raw())?[0]
+                .get_physical_device_surface_present_modes(*device.physical(), *self.as_raw())?
         };
 
-        let format = unsafe {
+        let formats = unsafe {
             self.loader
-                .get_physical_device_surface_formats(*device.physical(), *self.as_raw())?[0]
+                .get_physical_device_surface_formats(*device.physical(), *self.as_raw())?
         };
 
-        let infos = SurfaceInfos {
+        Ok(SurfaceSupport {
             capabilities,
-            present_mode,
-            format,
-        };
+            formats,
+            present_modes,
+        })
+    }
+
+    pub(crate) fn setup_infos(
+        &self,
+        device: Arc<Device>,
+        config: &SwapchainConfig,
+    ) -> Result<SurfaceInfos> {
+        let infos = config.resolve(&self.support(&device)?)?;
 
         *self.infos.write().unwrap() = Some(infos.clone());
 
@@ -97,3 +117,21 @@ impl Surface {
     }
 }
 
+
+/// the extent the surface reports, or the window size clamped to the supported range
+/// if the surface lets the swapchain decide
+pub fn choose_extent(
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+    window_size: vk::Extent2D,
+) -> vk::Extent2D {
+    if capabilities.current_extent.width != u32::MAX {
+        return capabilities.current_extent;
+    }
+
+    let min = capabilities.min_image_extent;
+    let max = capabilities.max_image_extent;
+    vk::Extent2D {
+        width: window_size.width.clamp(min.width, max.width.max(min.width)),
+        height: window_size.height.clamp(min.height, max.height.max(min.height)),
+    }
+}
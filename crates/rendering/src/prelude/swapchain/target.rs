@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use ash::vk;
+
+use crate::prelude::{Framebuffer, ImageView, Queue, Semaphore, Swapchain, SwapchainStatus};
+
+/// images that frames get acquired from and presented to,
+/// a window `Swapchain` or an offscreen `HeadlessTarget`
+pub trait RenderTarget {
+    /// `semaphore` is signaled once the image can be rendered to,
+    /// `None` if the target is out of date and has to be recreated
+    fn acquire_image(&self, semaphore: &Semaphore) -> Result<Option<(u32, SwapchainStatus)>>;
+
+    /// presents the image at `index` once `wait` is signaled
+    fn present_image(&self, index: u32, queue: &Queue, wait: &Semaphore)
+        -> Result<SwapchainStatus>;
+
+    fn images(&self) -> &[vk::Image];
+
+    /// one 2d color view per image, with the same index
+    fn image_views(&self) -> &[Arc<ImageView>];
+
+    fn framebuffer(&self, index: u32) -> Option<Arc<Framebuffer>>;
+
+    fn extent(&self) -> vk::Extent2D;
+
+    fn format(&self) -> vk::Format;
+
+    /// changes whenever the target got recreated
+    fn generation(&self) -> u64;
+
+    /// the layout images have to be in when they get presented
+    fn present_layout(&self) -> vk::ImageLayout;
+
+    /// returns the target that replaces this one, `None` if nothing can be rendered right now
+    fn recreate_target(self: Arc<Self>) -> Result<Option<Arc<dyn RenderTarget>>>;
+}
+
+impl RenderTarget for Swapchain {
+    fn acquire_image(&self, semaphore: &Semaphore) -> Result<Option<(u32, SwapchainStatus)>> {
+        Swapchain::acquire_image(self, semaphore)
+    }
+
+    fn present_image(
+        &self,
+        index: u32,
+        queue: &Queue,
+        wait: &Semaphore,
+    ) -> Result<SwapchainStatus> {
+        Swapchain::present_image(self, index, queue, wait)
+    }
+
+    fn images(&self) -> &[vk::Image] {
+        Swapchain::images(self)
+    }
+
+    fn image_views(&self) -> &[Arc<ImageView>] {
+        Swapchain::image_views(self)
+    }
+
+    fn framebuffer(&self, index: u32) -> Option<Arc<Framebuffer>> {
+        Swapchain::framebuffer(self, index)
+    }
+
+    fn extent(&self) -> vk::Extent2D {
+        Swapchain::extent(self)
+    }
+
+    fn format(&self) -> vk::Format {
+        Swapchain::format(self)
+    }
+
+    fn generation(&self) -> u64 {
+        Swapchain::generation(self)
+    }
+
+    fn present_layout(&self) -> vk::ImageLayout {
+        vk::ImageLayout::PRESENT_SRC_KHR
+    }
+
+    fn recreate_target(self: Arc<Self>) -> Result<Option<Arc<dyn RenderTarget>>> {
+        let swapchain = self.recreate()?;
+        Ok(swapchain.map(|v| v as Arc<dyn RenderTarget>))
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    any::Any,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::{CommandBuffer, Device, Queue, Submission};
+
+/// created signaled, so waiting on a fence that was never submitted returns right away
+/// resources of the submitted work are released the first time the fence is seen signaled
+pub struct Fence {
+    handle: vk::Fence,
+    device: Arc<Device>,
+    // owned by the submission that is running, released once the fence is seen signaled
+    pending_resources: Mutex<Vec<Box<dyn Any>>>,
+    // attached since the last submit, they go to the next one
+    staged_resources: Mutex<Vec<Box<dyn Any>>>,
+    // true between a submit and the wait or poll that sees the fence signaled
+    in_flight: AtomicBool,
+}
+
+impl Fence {
+    pub fn new(device: Arc<Device>) -> Result<Arc<Self>> {
+        let info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
+
+        let fence = unsafe { device.as_raw().create_fence(&info, None) }?;
+
+        Ok(Self {
+            handle: fence,
+            device,
+            pending_resources: vec![].into(),
+            staged_resources: vec![].into(),
+            in_flight: AtomicBool::new(false),
+        }
+        .into())
+    }
+
+    pub fn submit_command_buffers(
+        &self,
+        queue: &Queue,
+        command_buffers: Vec<CommandBuffer>,
+    ) -> Result<()> {
+        Submission::new(command_buffers).submit(queue, Some(self))
+    }
+
+    /// keeps `resource` alive until the work submitted with the fence finished,
+    /// use it for buffers, descriptor sets, pipelines and everything else the commands use
+    /// resources attached before a submit belong to that submit, even if the fence is still
+    /// waiting for an earlier one
+    pub fn keep_alive(&self, resource: impl Any) {
+        self.staged_resources
+            .lock()
+            .unwrap()
+            .push(Box::new(resource));
+    }
+
+    /// keeps `resource` alive for the submission that is already running
+    pub(crate) fn keep_alive_in_flight(&self, resource: impl Any) {
+        self.pending_resources
+            .lock()
+            .unwrap()
+            .push(Box::new(resource));
+    }
+
+    /// releases the resources of the last submission and resets the fence,
+    /// fails if the GPU isn't done with it yet
+    pub fn reset(&self) -> Result<()> {
+        if !self.is_signaled()? {
+            bail!("the fence can't be reset while its work is still running");
+        }
+
+        unsafe { self.device.as_raw().reset_fences(&[self.handle]) }?;
+        Ok(())
+    }
+
+    /// called right before the fence is submitted
+    /// the staged resources become the resources of the new submission
+    pub(crate) fn begin_submit(&self) -> Result<()> {
+        self.reset()?;
+
+        let staged = std::mem::take(&mut *self.staged_resources.lock().unwrap());
+        self.pending_resources.lock().unwrap().extend(staged);
+
+        self.in_flight.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// called if the submit failed, the fence stays unsignaled but nothing is running
+    pub(crate) fn cancel_submit(&self) {
+        self.in_flight.store(false, Ordering::Release);
+        self.release_resources();
+    }
+
+    fn release_resources(&self) {
+        let resources = std::mem::take(&mut *self.pending_resources.lock().unwrap());
+        drop(resources);
+    }
+
+    /// true once the GPU finished the submitted work, the resources are released then
+    /// a fence that was reset without being submitted again counts as signaled
+    pub fn is_signaled(&self) -> Result<bool> {
+        if !self.in_flight.load(Ordering::Acquire) {
+            return Ok(true);
+        }
+
+        let signaled = unsafe { self.device.as_raw().get_fence_status(self.handle) }?;
+        if signaled {
+            self.in_flight.store(false, Ordering::Release);
+            self.release_resources();
+        }
+        Ok(signaled)
+    }
+
+    /// blocks until the submitted work finished and releases its resources
+    pub fn wait_for_finished(&self) -> Result<()> {
+        if self.in_flight.load(Ordering::Acquire) {
+            unsafe {
+                self.device
+                    .as_raw()
+                    .wait_for_fences(&[self.handle], true, u64::MAX)
+            }?;
+            self.in_flight.store(false, Ordering::Release);
+            self.release_resources();
+        }
+
+        Ok(())
+    }
+
+    pub fn as_raw(&self) -> &vk::Fence {
+        &self.handle
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        // the resources can only go once the GPU is done with them
+        self.wait_for_finished().ok();
+        unsafe { self.device.as_raw().destroy_fence(self.handle, None) };
+    }
+}
@@ -0,0 +1,7 @@
+mod fence;
+mod semaphore;
+mod submit;
+
+pub use fence::*;
+pub use semaphore::*;
+pub use submit::*;
@@ -0,0 +1,160 @@
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::Device;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemaphoreKind {
+    /// signaled and waited on once per submission, only by the GPU
+    Binary,
+    /// a counter that only goes up, the host can wait for and signal values
+    Timeline,
+}
+
+pub struct Semaphore {
+    handle: vk::Semaphore,
+    device: Arc<Device>,
+    kind: SemaphoreKind,
+    // resources that are dropped once the counter reaches their value
+    pending_resources: Mutex<Vec<(u64, Box<dyn Any>)>>,
+}
+
+impl Semaphore {
+    pub fn binary(device: Arc<Device>) -> Result<Arc<Self>> {
+        let info = vk::SemaphoreCreateInfo::default();
+        let handle = unsafe { device.as_raw().create_semaphore(&info, None) }?;
+
+        Ok(Self::from_raw(device, handle, SemaphoreKind::Binary).into())
+    }
+
+    /// needs `Device::supports_timeline_semaphores`
+    pub fn timeline(device: Arc<Device>, initial_value: u64) -> Result<Arc<Self>> {
+        if !device.supports_timeline_semaphores() {
+            bail!("the device doesn't support timeline semaphores");
+        }
+
+        let mut type_info = vk::SemaphoreTypeCreateInfo::default()
+            .semaphore_type(vk::SemaphoreType::TIMELINE)
+            .initial_value(initial_value);
+        let info = vk::SemaphoreCreateInfo::default().push_next(&mut type_info);
+        let handle = unsafe { device.as_raw().create_semaphore(&info, None) }?;
+
+        Ok(Self::from_raw(device, handle, SemaphoreKind::Timeline).into())
+    }
+
+    fn from_raw(device: Arc<Device>, handle: vk::Semaphore, kind: SemaphoreKind) -> Self {
+        Self {
+            handle,
+            device,
+            kind,
+            pending_resources: Mutex::default(),
+        }
+    }
+
+    fn ensure_timeline(&self) -> Result<()> {
+        if self.kind != SemaphoreKind::Timeline {
+            bail!("only timeline semaphores have a value the host can use");
+        }
+        Ok(())
+    }
+
+    /// the current counter value of a timeline semaphore
+    pub fn value(&self) -> Result<u64> {
+        self.ensure_timeline()?;
+        let value = unsafe { self.device.as_raw().get_semaphore_counter_value(self.handle) }?;
+        Ok(value)
+    }
+
+    /// blocks until the counter reaches `value`, returns false if `timeout` ran out first
+    pub fn wait(&self, value: u64, timeout: Option<Duration>) -> Result<bool> {
+        self.ensure_timeline()?;
+
+        let semaphores = [self.handle];
+        let values = [value];
+        let info = vk::SemaphoreWaitInfo::default()
+            .semaphores(&semaphores)
+            .values(&values);
+        let timeout = timeout.map_or(u64::MAX, |v| v.as_nanos() as u64);
+
+        match unsafe { self.device.as_raw().wait_semaphores(&info, timeout) } {
+            Ok(()) => {}
+            Err(vk::Result::TIMEOUT) => return Ok(false),
+            Err(err) => return Err(err.into()),
+        }
+
+        self.release_finished()?;
+        Ok(true)
+    }
+
+    /// sets the counter to `value` from the host, it has to be bigger than the current value
+    pub fn signal(&self, value: u64) -> Result<()> {
+        self.ensure_timeline()?;
+
+        let info = vk::SemaphoreSignalInfo::default()
+            .semaphore(self.handle)
+            .value(value);
+        unsafe { self.device.as_raw().signal_semaphore(&info) }?;
+
+        self.release_finished()
+    }
+
+    /// keeps `resource` alive until the counter reaches `value`
+    pub fn release_at(&self, value: u64, resource: impl Any) -> Result<()> {
+        self.ensure_timeline()?;
+        self.keep_until(value, resource);
+        self.release_finished()
+    }
+
+    /// like `release_at` but can't fail, for resources of work that was already submitted
+    pub(crate) fn keep_until(&self, value: u64, resource: impl Any) {
+        self.pending_resources
+            .lock()
+            .unwrap()
+            .push((value, Box::new(resource)));
+    }
+
+    /// drops the resources whose value was reached
+    pub fn release_finished(&self) -> Result<()> {
+        let value = self.value()?;
+
+        // dropped outside of the lock, resources might hold semaphores themselves
+        let finished: Vec<_> = {
+            let mut pending = self.pending_resources.lock().unwrap();
+            let (finished, waiting) = std::mem::take(&mut *pending)
+                .into_iter()
+                .partition(|(v, _)| *v <= value);
+            *pending = waiting;
+            finished
+        };
+        drop(finished);
+
+        Ok(())
+    }
+
+    pub fn kind(&self) -> SemaphoreKind {
+        self.kind
+    }
+
+    pub fn as_raw(&self) -> &vk::Semaphore {
+        &self.handle
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        // the GPU might still run work that signals the semaphore and uses the resources
+        let pending = std::mem::take(self.pending_resources.get_mut().unwrap());
+        if let Some(value) = pending.iter().map(|(v, _)| *v).max() {
+            self.wait(value, None).ok();
+        }
+        drop(pending);
+
+        unsafe { self.device.as_raw().destroy_semaphore(self.handle, None) };
+    }
+}
@@ -0,0 +1,179 @@
+use std::{any::Any, sync::Arc};
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::{CommandBuffer, Fence, Queue, Semaphore, SemaphoreKind};
+
+pub struct SemaphoreWait {
+    pub semaphore: Arc<Semaphore>,
+    /// ignored for binary semaphores
+    pub value: u64,
+    /// the stages of the submission that wait
+    pub stage: vk::PipelineStageFlags,
+}
+
+pub struct SemaphoreSignal {
+    pub semaphore: Arc<Semaphore>,
+    /// ignored for binary semaphores
+    pub value: u64,
+}
+
+/// command buffers with the semaphores they wait for and signal
+/// the command buffers and semaphores are kept alive until the submission finished,
+/// that is tracked by the fence or by the highest timeline value that gets signaled
+pub struct Submission {
+    command_buffers: Vec<CommandBuffer>,
+    waits: Vec<SemaphoreWait>,
+    signals: Vec<SemaphoreSignal>,
+    resources: Vec<Box<dyn Any>>,
+}
+
+impl Submission {
+    pub fn new(command_buffers: Vec<CommandBuffer>) -> Self {
+        Self {
+            command_buffers,
+            waits: vec![],
+            signals: vec![],
+            resources: vec![],
+        }
+    }
+
+    /// waits for a binary semaphore
+    pub fn wait(self, semaphore: &Arc<Semaphore>, stage: vk::PipelineStageFlags) -> Self {
+        self.wait_value(semaphore, 0, stage)
+    }
+
+    /// waits until a timeline semaphore reaches `value`
+    pub fn wait_value(
+        mut self,
+        semaphore: &Arc<Semaphore>,
+        value: u64,
+        stage: vk::PipelineStageFlags,
+    ) -> Self {
+        self.waits.push(SemaphoreWait {
+            semaphore: semaphore.clone(),
+            value,
+            stage,
+        });
+        self
+    }
+
+    /// signals a binary semaphore
+    pub fn signal(self, semaphore: &Arc<Semaphore>) -> Self {
+        self.signal_value(semaphore, 0)
+    }
+
+    /// sets a timeline semaphore to `value` once the command buffers finished
+    pub fn signal_value(mut self, semaphore: &Arc<Semaphore>, value: u64) -> Self {
+        self.signals.push(SemaphoreSignal {
+            semaphore: semaphore.clone(),
+            value,
+        });
+        self
+    }
+
+    /// keeps `resource` alive until the submission finished
+    pub fn keep_alive(mut self, resource: impl Any) -> Self {
+        self.resources.push(Box::new(resource));
+        self
+    }
+
+    /// the timeline semaphore and value that mark the end of the submission, if there is one
+    fn last_timeline_signal(&self) -> Option<(&Arc<Semaphore>, u64)> {
+        self.signals
+            .iter()
+            .filter(|v| v.semaphore.kind() == SemaphoreKind::Timeline)
+            .max_by_key(|v| v.value)
+            .map(|v| (&v.semaphore, v.value))
+    }
+
+    fn validate(&self, queue: &Queue, fence: Option<&Fence>) -> Result<()> {
+        if let Some(buffer) = self
+            .command_buffers
+            .iter()
+            .find(|v| v.queue_family_index() != queue.family_index())
+        {
+            bail!(
+                "command buffer from queue family {} can't be submitted to a queue of family {}",
+                buffer.queue_family_index(),
+                queue.family_index()
+            );
+        }
+
+        if fence.is_none() && self.last_timeline_signal().is_none() {
+            bail!("a submission needs a fence or a timeline signal to know when it finished");
+        }
+
+        Ok(())
+    }
+
+    /// submits to `queue` and signals `fence` once everything finished
+    pub fn submit(self, queue: &Queue, fence: Option<&Fence>) -> Result<()> {
+        self.validate(queue, fence)?;
+
+        let raw_buffers: Vec<_> = self.command_buffers.iter().map(|v| *v.as_raw()).collect();
+        let wait_semaphores: Vec<_> = self.waits.iter().map(|v| *v.semaphore.as_raw()).collect();
+        let wait_values: Vec<_> = self.waits.iter().map(|v| v.value).collect();
+        let wait_stages: Vec<_> = self.waits.iter().map(|v| v.stage).collect();
+        let signal_semaphores: Vec<_> =
+            self.signals.iter().map(|v| *v.semaphore.as_raw()).collect();
+        let signal_values: Vec<_> = self.signals.iter().map(|v| v.value).collect();
+
+        // binary semaphores ignore their value
+        let mut timeline_info = vk::TimelineSemaphoreSubmitInfo::default()
+            .wait_semaphore_values(&wait_values)
+            .signal_semaphore_values(&signal_values);
+
+        let info = vk::SubmitInfo::default()
+            .command_buffers(&raw_buffers)
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .signal_semaphores(&signal_semaphores);
+        let uses_timeline = self
+            .waits
+            .iter()
+            .map(|v| &v.semaphore)
+            .chain(self.signals.iter().map(|v| &v.semaphore))
+            .any(|v| v.kind() == SemaphoreKind::Timeline);
+        let info = if uses_timeline {
+            info.push_next(&mut timeline_info)
+        } else {
+            info
+        };
+
+        if let Some(fence) = fence {
+            fence.begin_submit()?;
+        }
+        let submitted = queue.submit(&[info], fence.map_or(vk::Fence::null(), |v| *v.as_raw()));
+        if let (Err(_), Some(fence)) = (&submitted, fence) {
+            fence.cancel_submit();
+        }
+        submitted?;
+
+        match fence {
+            Some(fence) => {
+                fence.keep_alive_in_flight(self);
+                Ok(())
+            }
+            None => {
+                let (semaphore, value) = self.last_timeline_signal().unwrap();
+                let semaphore = semaphore.clone();
+
+                // the semaphore must not keep itself alive
+                let Self {
+                    command_buffers,
+                    mut waits,
+                    mut signals,
+                    resources,
+                } = self;
+                waits.retain(|v| !Arc::ptr_eq(&v.semaphore, &semaphore));
+                signals.retain(|v| !Arc::ptr_eq(&v.semaphore, &semaphore));
+
+                // the work is running already, so the resources must be stored no matter what
+                semaphore.keep_until(value, (command_buffers, waits, signals, resources));
+                Ok(())
+            }
+        }
+    }
+}
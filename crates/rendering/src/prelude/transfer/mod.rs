@@ -0,0 +1,430 @@
+mod readback;
+mod ring;
+
+pub use readback::*;
+pub use ring::*;
+
+use std::{collections::VecDeque, sync::Arc};
+
+use anyhow::{Context, Result};
+use ash::vk;
+
+use crate::prelude::{
+    Allocation, AllocationKind, AllocationRequest, Device, MemoryAllocator, MemoryCategory, Queue,
+};
+
+pub const STAGING_RING_SIZE: u64 = 16 * 1024 * 1024;
+
+// keeps copies within the ring aligned for every format
+const STAGING_ALIGNMENT: u64 = 16;
+
+/// a host visible buffer the data is copied into before the GPU copies it to its destination
+struct StagingBuffer {
+    handle: vk::Buffer,
+    allocation: Allocation,
+}
+
+impl StagingBuffer {
+    fn new(device: &ash::Device, allocator: &MemoryAllocator, size: u64) -> Result<Self> {
+        Self::with_category(device, allocator, size, MemoryCategory::Staging)
+    }
+
+    fn with_category(
+        device: &ash::Device,
+        allocator: &MemoryAllocator,
+        size: u64,
+        category: MemoryCategory,
+    ) -> Result<Self> {
+        let info = vk::BufferCreateInfo::default()
+            .size(size)
+            .usage(vk::BufferUsageFlags::TRANSFER_SRC | vk::BufferUsageFlags::TRANSFER_DST)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+
+        let handle = unsafe { device.create_buffer(&info, None) }?;
+        let requirements = unsafe { device.get_buffer_memory_requirements(handle) };
+
+        let allocation = allocator
+            .allocate(
+                device,
+                &AllocationRequest {
+                    requirements,
+                    visibility: vk::MemoryPropertyFlags::HOST_VISIBLE
+                        | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    kind: AllocationKind::Linear,
+                    dedicated: false,
+                    category,
+                },
+            )
+            .inspect_err(|_| unsafe { device.destroy_buffer(handle, None) })?;
+
+        let bind =
+            unsafe { device.bind_buffer_memory(handle, allocation.memory(), allocation.offset()) };
+        if let Err(err) = bind {
+            unsafe { device.destroy_buffer(handle, None) };
+            allocator.free(device, &allocation);
+            return Err(err.into());
+        }
+
+        Ok(Self { handle, allocation })
+    }
+
+    fn write(&self, offset: u64, data: &[u8]) {
+        let ptr = self.allocation.mapped_ptr().unwrap();
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                ptr.as_ptr().add(offset as usize),
+                data.len(),
+            )
+        };
+    }
+
+    fn read(&self, size: u64) -> Vec<u8> {
+        let ptr = self.allocation.mapped_ptr().unwrap();
+        unsafe { std::slice::from_raw_parts(ptr.as_ptr(), size as usize) }.to_vec()
+    }
+
+    fn destroy(self, device: &ash::Device, allocator: &MemoryAllocator) {
+        unsafe { device.destroy_buffer(self.handle, None) };
+        allocator.free(device, &self.allocation);
+    }
+}
+
+struct Submission {
+    id: u64,
+    fence: vk::Fence,
+    command_buffer: vk::CommandBuffer,
+    /// uploads that don't fit into the ring get their own staging buffer
+    oversized: Option<StagingBuffer>,
+}
+
+/// records and submits the copies of staged uploads
+/// it's owned by the `Device`, so it only keeps raw handles and gets the device passed in
+pub(crate) struct TransferContext {
+    pool: vk::CommandPool,
+    ring: StagingRing,
+    ring_buffer: StagingBuffer,
+    submissions: VecDeque<Submission>,
+    next_id: u64,
+}
+
+impl TransferContext {
+    pub(crate) fn new(
+        device: &ash::Device,
+        allocator: &MemoryAllocator,
+        queue_family_index: u32,
+    ) -> Result<Self> {
+        let info = vk::CommandPoolCreateInfo::default()
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(queue_family_index);
+
+        let pool = unsafe { device.create_command_pool(&info, None) }?;
+
+        let ring_buffer = StagingBuffer::new(device, allocator, STAGING_RING_SIZE)
+            .inspect_err(|_| unsafe { device.destroy_command_pool(pool, None) })?;
+
+        Ok(Self {
+            pool,
+            ring: StagingRing::new(STAGING_RING_SIZE),
+            ring_buffer,
+            submissions: VecDeque::new(),
+            next_id: 1,
+        })
+    }
+
+    /// copies `data` into staging memory and submits the commands recorded by `record`
+    /// `record` gets the staging buffer and the offset of the data inside of it
+    /// returns the id of the submission
+    pub(crate) fn submit(
+        &mut self,
+        device: &ash::Device,
+        allocator: &MemoryAllocator,
+        queue: &Queue,
+        data: &[u8],
+        record: impl FnOnce(vk::CommandBuffer, vk::Buffer, u64),
+    ) -> Result<u64> {
+        let id = self.next_id;
+        self.reclaim(device, allocator)?;
+
+        let mut oversized = None;
+        let (buffer, offset) = if data.len() as u64 > self.ring.size() {
+            let staging = StagingBuffer::new(device, allocator, data.len() as u64)
+                .context("failed to create staging buffer")?;
+            staging.write(0, data);
+            (oversized.insert(staging).handle, 0)
+        } else {
+            let offset = loop {
+                if let Some(offset) = self.ring.allocate(data.len() as u64, STAGING_ALIGNMENT, id) {
+                    break offset;
+                }
+                // the ring is full, wait for the oldest upload to make room
+                let oldest = self
+                    .submissions
+                    .front()
+                    .context("staging ring is full without pending uploads")?
+                    .id;
+                self.wait(device, allocator, oldest)?;
+            };
+            self.ring_buffer.write(offset, data);
+            (self.ring_buffer.handle, offset)
+        };
+
+        let result = self.record_and_submit(device, queue, |command_buffer| {
+            record(command_buffer, buffer, offset)
+        });
+
+        let (command_buffer, fence) = match result {
+            Ok(v) => v,
+            Err(err) => {
+                self.ring.cancel(id);
+                if let Some(staging) = oversized {
+                    staging.destroy(device, allocator);
+                }
+                return Err(err);
+            }
+        };
+
+        self.next_id += 1;
+        self.submissions.push_back(Submission {
+            id,
+            fence,
+            command_buffer,
+            oversized,
+        });
+
+        Ok(id)
+    }
+
+    /// records the copy into a new readback buffer, submits it and waits for it
+    pub(crate) fn readback(
+        &mut self,
+        device: &ash::Device,
+        allocator: &MemoryAllocator,
+        queue: &Queue,
+        size: u64,
+        record: impl FnOnce(vk::CommandBuffer, vk::Buffer),
+    ) -> Result<Vec<u8>> {
+        self.reclaim(device, allocator)?;
+
+        let readback =
+            StagingBuffer::with_category(device, allocator, size, MemoryCategory::Readback)
+                .context("failed to create readback buffer")?;
+
+        // the copy has to wait for earlier writes and the host has to see its result
+        let before = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+        let after = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ);
+
+        let result = self.record_and_submit(device, queue, |command_buffer| unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[before],
+                &[],
+                &[],
+            );
+            record(command_buffer, readback.handle);
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[after],
+                &[],
+                &[],
+            );
+        });
+
+        let (command_buffer, fence) = match result {
+            Ok(v) => v,
+            Err(err) => {
+                readback.destroy(device, allocator);
+                return Err(err);
+            }
+        };
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.submissions.push_back(Submission {
+            id,
+            fence,
+            command_buffer,
+            oversized: None,
+        });
+
+        let result = self.wait(device, allocator, id);
+        let data = readback.read(size);
+        readback.destroy(device, allocator);
+
+        result.map(|_| data)
+    }
+
+    fn record_and_submit(
+        &self,
+        device: &ash::Device,
+        queue: &Queue,
+        record: impl FnOnce(vk::CommandBuffer),
+    ) -> Result<(vk::CommandBuffer, vk::Fence)> {
+        let info = vk::CommandBufferAllocateInfo::default()
+            .command_pool(self.pool)
+            .command_buffer_count(1)
+            .level(vk::CommandBufferLevel::PRIMARY);
+
+        let command_buffer = unsafe { device.allocate_command_buffers(&info) }?[0];
+
+        let begin = vk::CommandBufferBeginInfo::default()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+        // later submissions on the queue have to see the copied data
+        let barrier = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE);
+
+        let result = unsafe {
+            device
+                .begin_command_buffer(command_buffer, &begin)
+                .and_then(|_| {
+                    record(command_buffer);
+                    device.cmd_pipeline_barrier(
+                        command_buffer,
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::PipelineStageFlags::ALL_COMMANDS,
+                        vk::DependencyFlags::empty(),
+                        &[barrier],
+                        &[],
+                        &[],
+                    );
+                    device.end_command_buffer(command_buffer)
+                })
+                .and_then(|_| device.create_fence(&vk::FenceCreateInfo::default(), None))
+        };
+
+        let fence = match result {
+            Ok(fence) => fence,
+            Err(err) => {
+                unsafe { device.free_command_buffers(self.pool, &[command_buffer]) };
+                return Err(err.into());
+            }
+        };
+
+        let submit =
+            vk::SubmitInfo::default().command_buffers(std::slice::from_ref(&command_buffer));
+
+        if let Err(err) = queue.submit(&[submit], fence) {
+            unsafe {
+                device.destroy_fence(fence, None);
+                device.free_command_buffers(self.pool, &[command_buffer]);
+            }
+            return Err(err).context("failed to submit transfer");
+        }
+
+        Ok((command_buffer, fence))
+    }
+
+    fn finish(
+        &mut self,
+        device: &ash::Device,
+        allocator: &MemoryAllocator,
+        submission: Submission,
+    ) {
+        self.ring.release(submission.id);
+        unsafe {
+            device.destroy_fence(submission.fence, None);
+            device.free_command_buffers(self.pool, &[submission.command_buffer]);
+        }
+        if let Some(staging) = submission.oversized {
+            staging.destroy(device, allocator);
+        }
+    }
+
+    /// cleans up every submission that already finished
+    pub(crate) fn reclaim(
+        &mut self,
+        device: &ash::Device,
+        allocator: &MemoryAllocator,
+    ) -> Result<()> {
+        // submissions on the same queue finish in order
+        while let Some(submission) = self.submissions.front() {
+            if !unsafe { device.get_fence_status(submission.fence) }? {
+                break;
+            }
+            let submission = self.submissions.pop_front().unwrap();
+            self.finish(device, allocator, submission);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_finished(
+        &mut self,
+        device: &ash::Device,
+        allocator: &MemoryAllocator,
+        id: u64,
+    ) -> Result<bool> {
+        self.reclaim(device, allocator)?;
+        Ok(self.submissions.front().is_none_or(|v| v.id > id))
+    }
+
+    /// blocks until the submission `id` and every submission before it finished
+    pub(crate) fn wait(
+        &mut self,
+        device: &ash::Device,
+        allocator: &MemoryAllocator,
+        id: u64,
+    ) -> Result<()> {
+        while let Some(submission) = self.submissions.front() {
+            if submission.id > id {
+                break;
+            }
+            unsafe { device.wait_for_fences(&[submission.fence], true, u64::MAX) }?;
+            let submission = self.submissions.pop_front().unwrap();
+            self.finish(device, allocator, submission);
+        }
+        Ok(())
+    }
+
+    /// the device has to be idle
+    pub(crate) fn destroy(mut self, device: &ash::Device, allocator: &MemoryAllocator) {
+        while let Some(submission) = self.submissions.pop_front() {
+            self.finish(device, allocator, submission);
+        }
+        self.ring_buffer.destroy(device, allocator);
+        unsafe { device.destroy_command_pool(self.pool, None) };
+    }
+}
+
+/// a pending upload, it can be waited on or polled with `is_finished`
+/// dropping it doesn't cancel the upload
+#[must_use]
+pub struct UploadHandle {
+    device: Arc<Device>,
+    id: u64,
+}
+
+impl UploadHandle {
+    pub(crate) fn new(device: Arc<Device>, id: u64) -> Self {
+        Self { device, id }
+    }
+
+    /// a handle for data that was written without a transfer
+    pub(crate) fn finished(device: Arc<Device>) -> Self {
+        Self { device, id: 0 }
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// blocks until the data arrived in the destination
+    pub fn wait(self) -> Result<()> {
+        self.device.wait_transfer(self.id)
+    }
+
+    pub fn is_finished(&self) -> Result<bool> {
+        self.device.is_transfer_finished(self.id)
+    }
+}
@@ -0,0 +1,220 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use anyhow::{bail, Result};
+use ash::vk;
+
+use crate::prelude::{record_transition, BufferAllocation, Device, Image};
+
+/// 8 bit RGBA pixels, rows are tightly packed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// size of one texel in bytes for the formats `RgbaImage::from_raw` can convert
+pub fn format_texel_size(format: vk::Format) -> Option<u64> {
+    Some(match format {
+        vk::Format::R8_UNORM | vk::Format::R8_SRGB => 1,
+        vk::Format::R8G8_UNORM | vk::Format::R8G8_SRGB => 2,
+        vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB
+        | vk::Format::A2B10G10R10_UNORM_PACK32
+        | vk::Format::R32_SFLOAT => 4,
+        vk::Format::R16G16B16A16_SFLOAT | vk::Format::R16G16B16A16_UNORM => 8,
+        vk::Format::R32G32B32A32_SFLOAT => 16,
+        _ => return None,
+    })
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+fn unorm(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+impl RgbaImage {
+    /// converts tightly packed texels of `format` to RGBA8
+    /// channels that the format doesn't have are 0, alpha is 255
+    /// float formats are clamped to [0, 1], values aren't converted between linear and sRGB
+    pub fn from_raw(format: vk::Format, width: u32, height: u32, data: &[u8]) -> Result<Self> {
+        let Some(texel_size) = format_texel_size(format) else {
+            bail!("can't convert {format:?} to RGBA8");
+        };
+
+        let expected = width as u64 * height as u64 * texel_size;
+        if data.len() as u64 != expected {
+            bail!(
+                "{width}x{height} {format:?} needs {expected} bytes but got {}",
+                data.len()
+            );
+        }
+
+        let u16_at = |texel: &[u8], i: usize| u16::from_le_bytes([texel[i * 2], texel[i * 2 + 1]]);
+        let f32_at = |texel: &[u8], i: usize| {
+            f32::from_le_bytes(texel[i * 4..i * 4 + 4].try_into().unwrap())
+        };
+
+        let pixels = data
+            .chunks_exact(texel_size as usize)
+            .flat_map(|texel| match format {
+                vk::Format::R8_UNORM | vk::Format::R8_SRGB => [texel[0], 0, 0, 255],
+                vk::Format::R8G8_UNORM | vk::Format::R8G8_SRGB => [texel[0], texel[1], 0, 255],
+                vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => {
+                    [texel[2], texel[1], texel[0], texel[3]]
+                }
+                vk::Format::A2B10G10R10_UNORM_PACK32 => {
+                    let packed = u32::from_le_bytes(texel.try_into().unwrap());
+                    let channel = |shift: u32| unorm((packed >> shift & 0x3ff) as f32 / 1023.0);
+                    [
+                        channel(0),
+                        channel(10),
+                        channel(20),
+                        ((packed >> 30) * 255 / 3) as u8,
+                    ]
+                }
+                vk::Format::R32_SFLOAT => [unorm(f32_at(texel, 0)), 0, 0, 255],
+                vk::Format::R16G16B16A16_SFLOAT => {
+                    std::array::from_fn(|i| unorm(f16_to_f32(u16_at(texel, i))))
+                }
+                vk::Format::R16G16B16A16_UNORM => {
+                    std::array::from_fn(|i| (u16_at(texel, i) >> 8) as u8)
+                }
+                vk::Format::R32G32B32A32_SFLOAT => std::array::from_fn(|i| unorm(f32_at(texel, i))),
+                _ => [texel[0], texel[1], texel[2], texel[3]],
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels[index..index + 4].try_into().unwrap()
+    }
+
+    /// writes the pixels as an 8 bit RGBA png
+    pub fn write_png(&self, writer: impl std::io::Write) -> Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl Device {
+    /// copies the content of `buffer` into a readback buffer and waits for it
+    /// the buffer needs `BufferUsageFlags::TRANSFER_SRC`
+    pub fn download_buffer<T: Copy>(&self, buffer: &impl BufferAllocation) -> Result<Vec<T>> {
+        let element_size = std::mem::size_of::<T>() as u64;
+        if element_size == 0 || !buffer.size().is_multiple_of(element_size) {
+            bail!(
+                "buffer of {} bytes doesn't hold a whole number of {}",
+                buffer.size(),
+                std::any::type_name::<T>()
+            );
+        }
+
+        let bytes = self.readback_transfer(buffer.size(), |command_buffer, dst| {
+            let region = vk::BufferCopy::default()
+                .src_offset(buffer.offset())
+                .size(buffer.size());
+            unsafe {
+                self.as_raw()
+                    .cmd_copy_buffer(command_buffer, buffer.buffer(), dst, &[region])
+            }
+        })?;
+
+        let len = (buffer.size() / element_size) as usize;
+        let mut data = Vec::<T>::with_capacity(len);
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                data.as_mut_ptr().cast::<u8>(),
+                bytes.len(),
+            );
+            data.set_len(len);
+        }
+
+        Ok(data)
+    }
+
+    /// copies mip level 0 of the first layer of `image` and converts it to RGBA8
+    /// the image is moved to `TRANSFER_SRC_OPTIMAL` for the copy and back to its tracked layout afterwards
+    /// the image needs `ImageUsageFlags::TRANSFER_SRC`
+    pub fn download_image(&self, image: &Image) -> Result<RgbaImage> {
+        let layout = image.layout();
+        let format = image.format();
+        let extent = image.extent();
+
+        let Some(texel_size) = format_texel_size(format) else {
+            bail!("can't download images with format {format:?}");
+        };
+        let size = extent.width as u64 * extent.height as u64 * texel_size;
+
+        let range = image.subresource_range();
+        let src_layout = vk::ImageLayout::TRANSFER_SRC_OPTIMAL;
+        // an undefined image has nothing to go back to
+        let final_layout = match layout {
+            vk::ImageLayout::UNDEFINED => src_layout,
+            layout => layout,
+        };
+
+        let bytes = self.readback_transfer(size, |command_buffer, dst| {
+            let region = vk::BufferImageCopy::default()
+                .image_subresource(
+                    vk::ImageSubresourceLayers::default()
+                        .aspect_mask(range.aspect_mask)
+                        .layer_count(1),
+                )
+                .image_extent(vk::Extent3D { depth: 1, ..extent });
+
+            let device = self.as_raw();
+            let handle = *image.as_raw();
+
+            record_transition(device, command_buffer, handle, range, layout, src_layout);
+            unsafe {
+                device.cmd_copy_image_to_buffer(command_buffer, handle, src_layout, dst, &[region])
+            };
+            if final_layout != src_layout {
+                record_transition(
+                    device,
+                    command_buffer,
+                    handle,
+                    range,
+                    src_layout,
+                    final_layout,
+                );
+            }
+        })?;
+        image.set_layout(final_layout);
+
+        RgbaImage::from_raw(format, extent.width, extent.height, &bytes)
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+
+use crate::prelude::align_up;
+
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    submission: u64,
+    end: u64,
+    /// bytes taken from the ring, including alignment padding and the skipped end when wrapping
+    consumed: u64,
+}
+
+/// hands out ranges of a staging buffer in submission order
+/// ranges are given back in the same order once their submission finished
+#[derive(Debug, Clone)]
+pub struct StagingRing {
+    size: u64,
+    head: u64,
+    tail: u64,
+    used: u64,
+    regions: VecDeque<Region>,
+}
+
+impl StagingRing {
+    pub fn new(size: u64) -> Self {
+        Self {
+            size,
+            head: 0,
+            tail: 0,
+            used: 0,
+            regions: VecDeque::new(),
+        }
+    }
+
+    /// returns the offset of `size` bytes that stay reserved until `release(submission)`
+    /// `None` if the ring is too full, which can be fixed by waiting for older submissions
+    pub fn allocate(&mut self, size: u64, alignment: u64, submission: u64) -> Option<u64> {
+        if size == 0 || size > self.size {
+            return None;
+        }
+
+        if self.used == 0 {
+            self.head = 0;
+            self.tail = 0;
+        }
+
+        let start = align_up(self.head, alignment);
+
+        let start = if self.head >= self.tail && self.used < self.size {
+            if start + size <= self.size {
+                start
+            } else if size <= self.tail {
+                // skip the rest of the ring and start over at the front
+                0
+            } else {
+                return None;
+            }
+        } else if start + size <= self.tail {
+            start
+        } else {
+            return None;
+        };
+
+        let end = start + size;
+        let consumed = if start >= self.head {
+            end - self.head
+        } else {
+            self.size - self.head + end
+        };
+
+        self.head = end;
+        self.used += consumed;
+        self.regions.push_back(Region {
+            submission,
+            end,
+            consumed,
+        });
+
+        Some(start)
+    }
+
+    /// gives back every range allocated for `submission` or an older one
+    pub fn release(&mut self, submission: u64) {
+        while let Some(region) = self.regions.front() {
+            if region.submission > submission {
+                break;
+            }
+            self.tail = region.end;
+            self.used -= region.consumed;
+            self.regions.pop_front();
+        }
+    }
+
+    /// undoes the allocations of `submission`, it has to be the newest one
+    pub fn cancel(&mut self, submission: u64) {
+        while self
+            .regions
+            .back()
+            .is_some_and(|v| v.submission == submission)
+        {
+            let region = self.regions.pop_back().unwrap();
+            self.used -= region.consumed;
+            self.head = self.regions.back().map_or(self.tail, |v| v.end);
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// bytes that are still reserved
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+}
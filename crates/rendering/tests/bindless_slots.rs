@@ -0,0 +1,71 @@
+use ash::vk;
+use rendering::prelude::*;
+
+#[test]
+fn freed_slots_are_reused() {
+    let mut slots = SlotAllocator::new(3);
+
+    assert_eq!(slots.allocate(), Some(0));
+    assert_eq!(slots.allocate(), Some(1));
+    assert_eq!(slots.allocate(), Some(2));
+    assert_eq!(slots.allocate(), None);
+    assert_eq!(slots.len(), 3);
+
+    slots.free(1).unwrap();
+    assert_eq!(slots.len(), 2);
+    assert_eq!(slots.allocate(), Some(1));
+    assert_eq!(slots.allocate(), None);
+}
+
+#[test]
+fn freeing_unused_slots_fails() {
+    let mut slots = SlotAllocator::new(8);
+    assert!(slots.free(0).is_err());
+
+    let index = slots.allocate().unwrap();
+    slots.free(index).unwrap();
+    assert!(slots.free(index).is_err());
+    assert!(slots.is_empty());
+}
+
+#[test]
+fn capacity_has_to_fit_the_limits() {
+    let limits = vk::PhysicalDeviceDescriptorIndexingProperties {
+        max_per_stage_descriptor_update_after_bind_sampled_images: 1024,
+        max_per_stage_descriptor_update_after_bind_storage_buffers: 4096,
+        max_per_stage_descriptor_update_after_bind_samplers: 64,
+        max_descriptor_set_update_after_bind_sampled_images: 4096,
+        max_descriptor_set_update_after_bind_storage_buffers: 4096,
+        max_descriptor_set_update_after_bind_samplers: 64,
+        max_per_stage_update_after_bind_resources: 8192,
+        ..Default::default()
+    };
+
+    let err = BindlessCapacity::default()
+        .check_limits(&limits)
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("maxPerStageDescriptorUpdateAfterBindSampledImages"));
+
+    let capacity = BindlessCapacity {
+        images: 1024,
+        ..Default::default()
+    };
+    capacity.check_limits(&limits).unwrap();
+
+    let capacity = BindlessCapacity {
+        images: 1024,
+        buffers: 4096,
+        samplers: 64,
+    };
+    let limits = vk::PhysicalDeviceDescriptorIndexingProperties {
+        max_per_stage_update_after_bind_resources: 4096,
+        ..limits
+    };
+    assert!(capacity
+        .check_limits(&limits)
+        .unwrap_err()
+        .to_string()
+        .contains("maxPerStageUpdateAfterBindResources"));
+}
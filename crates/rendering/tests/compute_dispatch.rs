@@ -0,0 +1,98 @@
+use rendering::prelude::*;
+
+fn host_buffer(usage: BufferUsageFlags) -> BufferCreateInfo<'static> {
+    BufferCreateInfo {
+        usage,
+        share_mode: BufferSharingMode::Exclusive,
+        visibility: MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        category: MemoryCategory::Buffer,
+    }
+}
+
+// see shader_reflection.rs for the source of double.comp
+#[test]
+fn dispatch_and_read_back() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+
+    let shader =
+        ShaderModule::from_spirv_bytes(device.clone(), include_bytes!("shaders/double.comp.spv"))
+            .unwrap();
+
+    let descriptors = [
+        DescriptorType {
+            binding: 0,
+            count: 1,
+            ty: VkDescriptorType::STORAGE_BUFFER,
+            stage_flags: ShaderStageFlags::COMPUTE,
+        },
+        DescriptorType {
+            binding: 1,
+            count: 1,
+            ty: VkDescriptorType::UNIFORM_BUFFER,
+            stage_flags: ShaderStageFlags::COMPUTE,
+        },
+    ];
+    let layout = DescriptorSetLayout::new(device.clone(), &descriptors).unwrap();
+    let pool = DescriptorPool::new(device.clone(), &descriptors).unwrap();
+    let sets = DescriptorSets::new(pool, &[layout.clone()]).unwrap();
+
+    let push_constants = vk::PushConstantRange {
+        stage_flags: ShaderStageFlags::COMPUTE,
+        offset: 0,
+        size: 8,
+    };
+    let pipeline =
+        ComputePipeline::new(device.clone(), shader, "main", &[layout], &[push_constants]).unwrap();
+
+    let values: Vec<u32> = (0..128).collect();
+    let data = Subbuffer::from_data(
+        device.clone(),
+        host_buffer(BufferUsageFlags::STORAGE_BUFFER),
+        &values,
+    )
+    .unwrap();
+    let params = Subbuffer::from_data(
+        device.clone(),
+        host_buffer(BufferUsageFlags::UNIFORM_BUFFER),
+        &[0u32],
+    )
+    .unwrap();
+
+    sets.write(
+        0,
+        &[
+            DescriptorWrite::storage_buffer(0, data.clone()),
+            DescriptorWrite::uniform_buffer(1, params),
+        ],
+    )
+    .unwrap();
+
+    // only the first 100 values get doubled
+    let count = 100u32;
+    let push = [count.to_le_bytes(), 1.0f32.to_le_bytes()].concat();
+
+    let pool = CommandPool::new(device.clone()).unwrap();
+    let command_buffer = CommandBuffer::new(pool, device.clone()).unwrap();
+    command_buffer.begin();
+    command_buffer.bind_compute_pipeline(&pipeline);
+    command_buffer
+        .bind_descriptor_sets(&*pipeline, 0, &sets)
+        .unwrap();
+    command_buffer.push_constants(&*pipeline, ShaderStageFlags::COMPUTE, 0, &push);
+    command_buffer.dispatch(2, 1, 1);
+    command_buffer.end();
+
+    let fence = Fence::new(device.clone()).unwrap();
+    fence
+        .submit_command_buffers(device.queue(), vec![command_buffer])
+        .unwrap();
+
+    // the buffer is bound through the descriptor sets, so it's busy until the fence was waited on
+    assert!(data.read().is_err());
+    fence.wait_for_finished().unwrap();
+
+    let result = data.read().unwrap();
+    assert_eq!(result[99], 198);
+    assert_eq!(result[100], 100);
+}
@@ -13,7 +13,7 @@ fn create_surface() {
 
     let fence = Fence::new(device.clone()).unwrap();
 
-    let cmd_alloc = CommandBufferAllocator::new(device.clone()).unwrap();
+    let cmd_alloc = CommandPool::new(device.clone()).unwrap();
 
     let command_buffer = CommandBuffer::new(cmd_alloc.clone(), device.clone()).unwrap();
 
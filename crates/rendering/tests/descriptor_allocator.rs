@@ -0,0 +1,84 @@
+use ash::vk;
+use rendering::prelude::*;
+
+fn binding(binding: u32, ty: vk::DescriptorType, count: u32) -> DescriptorType {
+    DescriptorType {
+        ty,
+        stage_flags: vk::ShaderStageFlags::COMPUTE,
+        count,
+        binding,
+    }
+}
+
+#[test]
+fn pool_sizes_scale_with_the_set_count() {
+    let per_set = [
+        vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::UNIFORM_BUFFER,
+            descriptor_count: 2,
+        },
+        vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 4,
+        },
+    ];
+
+    let sizes = descriptor_pool_sizes(&per_set, 16, &[]);
+    assert_eq!(sizes[0].descriptor_count, 32);
+    assert_eq!(sizes[1].descriptor_count, 64);
+}
+
+#[test]
+fn pool_sizes_fit_the_required_bindings() {
+    let per_set = [vk::DescriptorPoolSize {
+        ty: vk::DescriptorType::UNIFORM_BUFFER,
+        descriptor_count: 1,
+    }];
+    let required = [
+        binding(0, vk::DescriptorType::UNIFORM_BUFFER, 8),
+        binding(1, vk::DescriptorType::STORAGE_BUFFER, 3),
+        binding(2, vk::DescriptorType::UNIFORM_BUFFER, 8),
+    ];
+
+    let sizes = descriptor_pool_sizes(&per_set, 4, &required);
+    assert_eq!(sizes.len(), 2);
+    assert_eq!(sizes[0].ty, vk::DescriptorType::UNIFORM_BUFFER);
+    assert_eq!(sizes[0].descriptor_count, 16);
+    assert_eq!(sizes[1].ty, vk::DescriptorType::STORAGE_BUFFER);
+    assert_eq!(sizes[1].descriptor_count, 3);
+}
+
+#[test]
+fn pools_grow_up_to_a_limit() {
+    assert_eq!(next_pool_capacity(16), 24);
+    assert_eq!(next_pool_capacity(24), 36);
+    assert_eq!(next_pool_capacity(4000), 4096);
+}
+
+#[test]
+fn update_after_bind_layouts_get_their_own_pools() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+    if !device.supports_descriptor_indexing() {
+        return;
+    }
+
+    let descriptors = [binding(0, vk::DescriptorType::STORAGE_BUFFER, 4)];
+    let layout = DescriptorSetLayout::new(device.clone(), &descriptors).unwrap();
+    let update_after_bind = DescriptorSetLayout::with_binding_flags(
+        device.clone(),
+        &descriptors,
+        &[vk::DescriptorBindingFlags::UPDATE_AFTER_BIND],
+    )
+    .unwrap();
+
+    let per_set = [vk::DescriptorPoolSize {
+        ty: vk::DescriptorType::STORAGE_BUFFER,
+        descriptor_count: 4,
+    }];
+    let allocator = DescriptorAllocator::new(device.clone(), &per_set).unwrap();
+
+    let _sets = allocator.allocate(&[layout]).unwrap();
+    let _bindless = allocator.allocate(&[update_after_bind]).unwrap();
+    assert_eq!(allocator.pool_count(), 2);
+}
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use ash::vk::{self, Handle};
+use rendering::prelude::*;
+
+struct MockBuffer(vk::BufferUsageFlags);
+
+impl BufferAllocation for MockBuffer {
+    fn size(&self) -> vk::DeviceSize {
+        256
+    }
+    fn buffer(&self) -> vk::Buffer {
+        vk::Buffer::from_raw(1)
+    }
+    fn usage(&self) -> vk::BufferUsageFlags {
+        self.0
+    }
+}
+
+fn binding(binding: u32, ty: vk::DescriptorType, count: u32) -> DescriptorType {
+    DescriptorType {
+        ty,
+        stage_flags: vk::ShaderStageFlags::FRAGMENT,
+        count,
+        binding,
+    }
+}
+
+#[test]
+fn buffer_writes_cover_the_whole_buffer() {
+    let write = DescriptorWrite::storage_buffer(
+        3,
+        Arc::new(MockBuffer(vk::BufferUsageFlags::STORAGE_BUFFER)),
+    )
+    .array_element(2);
+
+    assert_eq!(write.binding, 3);
+    assert_eq!(write.array_element, 2);
+    assert_eq!(
+        write.resource.descriptor_type(),
+        vk::DescriptorType::STORAGE_BUFFER
+    );
+
+    let DescriptorResource::StorageBuffer { range, .. } = write.resource else {
+        panic!("expected a storage buffer");
+    };
+    assert_eq!(range, 0..256);
+}
+
+#[test]
+fn uniform_buffer_with_range() {
+    let write = DescriptorWrite::new(
+        0,
+        DescriptorResource::UniformBuffer {
+            buffer: Arc::new(MockBuffer(vk::BufferUsageFlags::UNIFORM_BUFFER)),
+            range: 64..128,
+        },
+    );
+
+    assert_eq!(
+        write.resource.descriptor_type(),
+        vk::DescriptorType::UNIFORM_BUFFER
+    );
+    assert_eq!(write.array_element, 0);
+}
+
+#[test]
+fn validates_writes_against_the_layout() {
+    let bindings = [
+        binding(0, vk::DescriptorType::UNIFORM_BUFFER, 1),
+        binding(1, vk::DescriptorType::STORAGE_BUFFER, 4),
+    ];
+    let storage = Arc::new(MockBuffer(vk::BufferUsageFlags::STORAGE_BUFFER));
+
+    assert!(DescriptorWrite::storage_buffer(1, storage.clone())
+        .array_element(3)
+        .validate(&bindings)
+        .is_ok());
+
+    let err = DescriptorWrite::storage_buffer(2, storage.clone())
+        .validate(&bindings)
+        .unwrap_err();
+    assert!(err.to_string().contains("no binding 2"));
+
+    let err = DescriptorWrite::storage_buffer(0, storage.clone())
+        .validate(&bindings)
+        .unwrap_err();
+    assert!(err.to_string().contains("UNIFORM_BUFFER"));
+
+    let err = DescriptorWrite::storage_buffer(1, storage)
+        .array_element(4)
+        .validate(&bindings)
+        .unwrap_err();
+    assert!(err.to_string().contains("out of range"));
+
+    let err = DescriptorWrite::storage_buffer(
+        1,
+        Arc::new(MockBuffer(vk::BufferUsageFlags::UNIFORM_BUFFER)),
+    )
+    .validate(&bindings)
+    .unwrap_err();
+    assert!(err.to_string().contains("STORAGE_BUFFER usage"));
+
+    let err = DescriptorWrite::new(
+        0,
+        DescriptorResource::UniformBuffer {
+            buffer: Arc::new(MockBuffer(vk::BufferUsageFlags::UNIFORM_BUFFER)),
+            range: 128..512,
+        },
+    )
+    .validate(&bindings)
+    .unwrap_err();
+    assert!(err.to_string().contains("doesn't fit"));
+}
@@ -0,0 +1,98 @@
+use ash::vk;
+use rendering::prelude::*;
+
+fn family(flags: vk::QueueFlags) -> vk::QueueFamilyProperties {
+    vk::QueueFamilyProperties {
+        queue_flags: flags,
+        queue_count: 1,
+        ..Default::default()
+    }
+}
+
+fn properties(device_type: vk::PhysicalDeviceType, max_image: u32) -> vk::PhysicalDeviceProperties {
+    let mut properties = vk::PhysicalDeviceProperties {
+        device_type,
+        ..Default::default()
+    };
+    properties.limits.max_image_dimension2_d = max_image;
+    properties
+}
+
+#[test]
+fn prefers_dedicated_queue_families() {
+    use vk::QueueFlags as Q;
+    let families = [
+        family(Q::GRAPHICS | Q::COMPUTE | Q::TRANSFER),
+        family(Q::COMPUTE | Q::TRANSFER),
+        family(Q::TRANSFER),
+    ];
+
+    let selected = QueueFamilies::select(&families, true, |v| v == 0).unwrap();
+    assert_eq!(
+        selected,
+        QueueFamilies {
+            graphics: 0,
+            compute: 1,
+            transfer: 2,
+            present: Some(0),
+        }
+    );
+    assert_eq!(selected.unique(), vec![0, 1, 2]);
+}
+
+#[test]
+fn falls_back_to_the_graphics_family() {
+    use vk::QueueFlags as Q;
+    let families = [
+        family(Q::TRANSFER | Q::SPARSE_BINDING),
+        family(Q::GRAPHICS | Q::COMPUTE | Q::TRANSFER),
+    ];
+
+    let selected = QueueFamilies::select(&families, false, |_| true).unwrap();
+    assert_eq!(selected.graphics, 1);
+    assert_eq!(selected.compute, 1);
+    assert_eq!(selected.transfer, 0);
+    assert_eq!(selected.present, None);
+
+    // present from another family if the graphics one can't
+    let selected = QueueFamilies::select(&families, true, |v| v == 0).unwrap();
+    assert_eq!(selected.present, Some(0));
+    assert_eq!(selected.unique(), vec![1, 0]);
+
+    assert!(QueueFamilies::select(&families, true, |_| false).is_none());
+    assert!(QueueFamilies::select(&families[..1], false, |_| true).is_none());
+}
+
+#[test]
+fn discrete_gpus_score_highest() {
+    let discrete = score_physical_device(&properties(vk::PhysicalDeviceType::DISCRETE_GPU, 4096));
+    let integrated =
+        score_physical_device(&properties(vk::PhysicalDeviceType::INTEGRATED_GPU, 16384));
+    let cpu = score_physical_device(&properties(vk::PhysicalDeviceType::CPU, 16384));
+    let bigger = score_physical_device(&properties(vk::PhysicalDeviceType::DISCRETE_GPU, 8192));
+
+    assert!(discrete > integrated);
+    assert!(integrated > cpu);
+    assert!(bigger > discrete);
+}
+
+#[test]
+fn checks_required_features() {
+    let supported = vk::PhysicalDeviceFeatures {
+        shader_clip_distance: vk::TRUE,
+        sampler_anisotropy: vk::TRUE,
+        ..Default::default()
+    };
+    let required = vk::PhysicalDeviceFeatures {
+        sampler_anisotropy: vk::TRUE,
+        ..Default::default()
+    };
+    let missing = vk::PhysicalDeviceFeatures {
+        geometry_shader: vk::TRUE,
+        ..Default::default()
+    };
+
+    assert!(supports_features(&required, &supported));
+    assert!(supports_features(&Default::default(), &supported));
+    assert!(!supports_features(&missing, &supported));
+}
@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use rendering::prelude::*;
+
+#[test]
+fn staged_resources_belong_to_the_next_submit() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+    let fence = Fence::new(device.clone()).unwrap();
+
+    let resource = Arc::new(());
+    fence.keep_alive(resource.clone());
+
+    // nothing was submitted yet, so seeing the fence signaled doesn't release it
+    assert!(fence.is_signaled().unwrap());
+    assert_eq!(Arc::strong_count(&resource), 2);
+
+    fence
+        .submit_command_buffers(device.queue(), vec![])
+        .unwrap();
+    fence.wait_for_finished().unwrap();
+    assert_eq!(Arc::strong_count(&resource), 1);
+}
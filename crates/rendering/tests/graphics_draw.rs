@@ -0,0 +1,134 @@
+use ash::vk;
+use rendering::prelude::*;
+
+fn host_buffer(usage: BufferUsageFlags) -> BufferCreateInfo<'static> {
+    BufferCreateInfo {
+        usage,
+        share_mode: BufferSharingMode::Exclusive,
+        visibility: MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+        category: MemoryCategory::Buffer,
+    }
+}
+
+// see shader_reflection.rs for the inputs of textured.vert
+// there is no fragment stage, the draw only has to go through the vertex input
+#[test]
+fn draws_with_vertex_and_index_buffers() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+    if !device.supports_dynamic_rendering() {
+        return;
+    }
+
+    let extent = vk::Extent2D {
+        width: 4,
+        height: 4,
+    };
+    let format = vk::Format::R8G8B8A8_UNORM;
+    let target = HeadlessTarget::new(device.clone(), extent, format, 1).unwrap();
+
+    let shader =
+        ShaderModule::from_spirv_bytes(device.clone(), include_bytes!("shaders/textured.vert.spv"))
+            .unwrap();
+    let descriptors = [DescriptorType {
+        binding: 0,
+        count: 1,
+        ty: VkDescriptorType::UNIFORM_BUFFER,
+        stage_flags: ShaderStageFlags::VERTEX,
+    }];
+    let layout = DescriptorSetLayout::new(device.clone(), &descriptors).unwrap();
+    let pool = DescriptorPool::new(device.clone(), &descriptors).unwrap();
+    let sets = DescriptorSets::new(pool, &[layout.clone()]).unwrap();
+
+    let pipeline = GraphicsPipeline::builder()
+        .shader_stage(shader, "main")
+        .unwrap()
+        .vertex_binding(0, 20, vk::VertexInputRate::VERTEX)
+        .vertex_attribute(0, 0, vk::Format::R32G32B32_SFLOAT, 0)
+        .vertex_attribute(1, 0, vk::Format::R32G32_SFLOAT, 12)
+        .descriptor_set_layout(layout)
+        .push_constant_range(vk::PushConstantRange {
+            stage_flags: ShaderStageFlags::VERTEX,
+            offset: 0,
+            size: 64,
+        })
+        .dynamic_rendering(&[format], vk::Format::UNDEFINED, vk::Format::UNDEFINED)
+        .build(device.clone())
+        .unwrap();
+
+    let identity: [f32; 16] = std::array::from_fn(|i| if i % 5 == 0 { 1.0 } else { 0.0 });
+    let camera = Subbuffer::from_data(
+        device.clone(),
+        host_buffer(BufferUsageFlags::UNIFORM_BUFFER),
+        &identity,
+    )
+    .unwrap();
+    sets.write(0, &[DescriptorWrite::uniform_buffer(0, camera)])
+        .unwrap();
+
+    #[rustfmt::skip]
+    let vertices: [f32; 15] = [
+        -1.0, -1.0, 0.0, 0.0, 0.0,
+        3.0, -1.0, 0.0, 2.0, 0.0,
+        -1.0, 3.0, 0.0, 0.0, 2.0,
+    ];
+    let vertices = Subbuffer::from_data(
+        device.clone(),
+        host_buffer(BufferUsageFlags::VERTEX_BUFFER),
+        &vertices,
+    )
+    .unwrap();
+    let indices = Subbuffer::from_data(
+        device.clone(),
+        host_buffer(BufferUsageFlags::INDEX_BUFFER),
+        &[0u16, 1, 2],
+    )
+    .unwrap();
+
+    let push: Vec<u8> = identity.iter().flat_map(|v| v.to_le_bytes()).collect();
+
+    let pool = CommandPool::new(device.clone()).unwrap();
+    let command_buffer = CommandBuffer::new(pool, device.clone()).unwrap();
+    command_buffer.begin();
+    command_buffer.transition_layout(
+        target.image(0).unwrap(),
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    );
+    {
+        let attachments = RenderingAttachments::default()
+            .color(RenderingAttachment::from_view(target.view(0).unwrap()).clear_color([0.0; 4]));
+        let _scope = command_buffer.rendering(extent, &attachments).unwrap();
+
+        command_buffer.bind_graphics_pipeline(&pipeline);
+        command_buffer.set_viewport(vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: extent.width as f32,
+            height: extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        });
+        command_buffer.set_scissor(extent.into());
+        command_buffer
+            .bind_descriptor_sets(&*pipeline, 0, &sets)
+            .unwrap();
+        command_buffer.push_constants(&*pipeline, ShaderStageFlags::VERTEX, 0, &push);
+        command_buffer.bind_vertex_buffers(0, &[&*vertices]);
+        command_buffer.bind_index_buffer(&*indices, 0, vk::IndexType::UINT16);
+        command_buffer.draw_indexed(3, 1, 0, 0, 0);
+    }
+    command_buffer.end();
+
+    let fence = Fence::new(device.clone()).unwrap();
+    fence
+        .submit_command_buffers(device.queue(), vec![command_buffer])
+        .unwrap();
+
+    // the bound buffers are busy until the fence was waited on
+    assert!(vertices.read().is_err());
+    assert!(indices.read().is_err());
+    fence.wait_for_finished().unwrap();
+
+    assert_eq!(vertices.read().unwrap()[5], 3.0);
+    assert_eq!(indices.read().unwrap()[2], 2);
+}
@@ -0,0 +1,47 @@
+use ash::vk;
+use rendering::prelude::*;
+
+#[test]
+fn renders_frames_without_a_window() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+    if !device.supports_dynamic_rendering() {
+        return;
+    }
+
+    let extent = vk::Extent2D {
+        width: 4,
+        height: 4,
+    };
+    let target =
+        HeadlessTarget::new(device.clone(), extent, vk::Format::R8G8B8A8_UNORM, 2).unwrap();
+    let mut frames = FrameContext::new(device.clone(), target.clone(), 2).unwrap();
+
+    // every frame gets its own color, so the readback shows which image was presented
+    let colors = [
+        [1.0, 0.0, 0.0, 1.0],
+        [0.0, 0.0, 1.0, 1.0],
+        [0.0, 1.0, 0.0, 1.0],
+    ];
+    for color in colors {
+        let frame = frames.begin_frame().unwrap().unwrap();
+        {
+            let attachments = RenderingAttachments::default()
+                .color(RenderingAttachment::from_view(&frame.view).clear_color(color));
+            let _scope = frame
+                .command_buffer
+                .rendering(frame.extent, &attachments)
+                .unwrap();
+        }
+        frames.end_frame(frame).unwrap();
+    }
+
+    let index = target.last_presented().unwrap();
+    assert_eq!(index, 0);
+    let image = target.read_image(index).unwrap();
+    assert_eq!((image.width, image.height), (4, 4));
+    assert_eq!(image.pixel(3, 3), [0, 255, 0, 255]);
+
+    let image = target.read_image(1).unwrap();
+    assert_eq!(image.pixel(0, 0), [0, 0, 255, 255]);
+}
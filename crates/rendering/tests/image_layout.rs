@@ -0,0 +1,45 @@
+use ash::vk;
+use rendering::prelude::*;
+
+#[test]
+fn counts_mip_levels() {
+    assert_eq!(mip_level_count(1, 1), 1);
+    assert_eq!(mip_level_count(256, 256), 9);
+    assert_eq!(mip_level_count(300, 20), 9);
+    assert_eq!(mip_level_count(1, 1024), 11);
+    assert_eq!(mip_level_count(0, 0), 1);
+}
+
+#[test]
+fn finds_aspect_of_format() {
+    assert_eq!(
+        format_aspect(vk::Format::R8G8B8A8_SRGB),
+        vk::ImageAspectFlags::COLOR
+    );
+    assert_eq!(
+        format_aspect(vk::Format::D32_SFLOAT),
+        vk::ImageAspectFlags::DEPTH
+    );
+    assert_eq!(
+        format_aspect(vk::Format::D24_UNORM_S8_UINT),
+        vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+    );
+}
+
+#[test]
+fn layouts_map_to_their_accesses() {
+    let (stage, access) = layout_access(vk::ImageLayout::UNDEFINED);
+    assert_eq!(stage, vk::PipelineStageFlags::TOP_OF_PIPE);
+    assert!(access.is_empty());
+
+    let (stage, access) = layout_access(vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+    assert_eq!(stage, vk::PipelineStageFlags::TRANSFER);
+    assert_eq!(access, vk::AccessFlags::TRANSFER_WRITE);
+
+    let (stage, access) = layout_access(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+    assert!(stage.contains(vk::PipelineStageFlags::FRAGMENT_SHADER));
+    assert_eq!(access, vk::AccessFlags::SHADER_READ);
+
+    let (stage, _) = layout_access(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+    assert_eq!(stage, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT);
+}
@@ -0,0 +1,340 @@
+use std::{cell::RefCell, collections::HashMap, ptr::NonNull};
+
+use ash::vk::{self, Handle};
+use rendering::prelude::*;
+
+const MIB: u64 = 1024 * 1024;
+
+/// hands out fake memory handles and keeps track of what is still allocated
+#[derive(Default)]
+struct MockBackend {
+    next: RefCell<u64>,
+    live: RefCell<HashMap<u64, Vec<u8>>>,
+}
+
+impl MockBackend {
+    fn live_count(&self) -> usize {
+        self.live.borrow().len()
+    }
+}
+
+impl MemoryBackend for MockBackend {
+    fn allocate_memory(
+        &self,
+        _memory_type_index: u32,
+        size: u64,
+    ) -> Result<vk::DeviceMemory, vk::Result> {
+        let mut next = self.next.borrow_mut();
+        *next += 1;
+        self.live.borrow_mut().insert(*next, vec![0; size as usize]);
+        Ok(vk::DeviceMemory::from_raw(*next))
+    }
+
+    fn map_memory(&self, memory: vk::DeviceMemory) -> Result<NonNull<u8>, vk::Result> {
+        let mut live = self.live.borrow_mut();
+        let data = live.get_mut(&memory.as_raw()).unwrap();
+        Ok(NonNull::new(data.as_mut_ptr()).unwrap())
+    }
+
+    fn free_memory(&self, memory: vk::DeviceMemory, _mapped: bool) {
+        assert!(self.live.borrow_mut().remove(&memory.as_raw()).is_some());
+    }
+}
+
+// type 0 is device local, type 1 is host visible on a small heap
+fn properties() -> vk::PhysicalDeviceMemoryProperties {
+    let mut properties = vk::PhysicalDeviceMemoryProperties {
+        memory_type_count: 2,
+        memory_heap_count: 2,
+        ..Default::default()
+    };
+    properties.memory_types[0] = vk::MemoryType {
+        property_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        heap_index: 0,
+    };
+    properties.memory_types[1] = vk::MemoryType {
+        property_flags: vk::MemoryPropertyFlags::HOST_VISIBLE
+            | vk::MemoryPropertyFlags::HOST_COHERENT,
+        heap_index: 1,
+    };
+    properties.memory_heaps[0].size = 8 * 1024 * MIB;
+    properties.memory_heaps[1].size = 64 * MIB;
+    properties
+}
+
+fn request(size: u64, visibility: vk::MemoryPropertyFlags) -> AllocationRequest {
+    AllocationRequest {
+        requirements: vk::MemoryRequirements {
+            size,
+            alignment: 256,
+            memory_type_bits: 0b11,
+        },
+        visibility,
+        kind: AllocationKind::Linear,
+        dedicated: false,
+        category: MemoryCategory::Buffer,
+    }
+}
+
+#[test]
+fn free_list_merges_neighbours() {
+    let mut list = FreeList::new(1024, 1);
+
+    let a = list.allocate(256, 1, AllocationKind::Linear).unwrap();
+    let b = list.allocate(256, 1, AllocationKind::Linear).unwrap();
+    let c = list.allocate(256, 1, AllocationKind::Linear).unwrap();
+    assert_eq!(list.used(), 768);
+
+    assert!(list.free(a));
+    assert!(list.free(c));
+    assert_eq!(list.largest_free_range(), 512);
+
+    assert!(list.free(b));
+    assert!(!list.free(b));
+    assert!(list.is_empty());
+    assert_eq!(list.largest_free_range(), 1024);
+}
+
+#[test]
+fn free_list_respects_alignment_and_granularity() {
+    let mut list = FreeList::new(4096, 1024);
+
+    let buffer = list.allocate(100, 16, AllocationKind::Linear).unwrap();
+    assert_eq!(buffer, 0);
+
+    // an optimal image can't share a granularity page with a buffer
+    let image = list.allocate(100, 16, AllocationKind::Optimal).unwrap();
+    assert_eq!(image, 1024);
+
+    let other = list.allocate(100, 64, AllocationKind::Linear).unwrap();
+    assert_eq!(other % 64, 0);
+    assert!(other >= 2048 || other + 100 <= 1024);
+
+    assert!(list.allocate(4096, 1, AllocationKind::Linear).is_none());
+}
+
+#[test]
+fn finds_memory_type_index() {
+    let properties = properties();
+    let requirements = vk::MemoryRequirements {
+        memory_type_bits: 0b11,
+        ..Default::default()
+    };
+
+    let host = vk::MemoryPropertyFlags::HOST_VISIBLE;
+    assert_eq!(
+        find_memorytype_index(&requirements, &properties, host),
+        Some(1)
+    );
+
+    let requirements = vk::MemoryRequirements {
+        memory_type_bits: 0b01,
+        ..requirements
+    };
+    assert_eq!(
+        find_memorytype_index(&requirements, &properties, host),
+        None
+    );
+}
+
+#[test]
+fn suballocates_from_one_block() {
+    let backend = MockBackend::default();
+    let allocator = MemoryAllocator::new(properties(), 1).with_block_size(16 * MIB);
+
+    let device_local = vk::MemoryPropertyFlags::DEVICE_LOCAL;
+    let a = allocator
+        .allocate(&backend, &request(MIB, device_local))
+        .unwrap();
+    let b = allocator
+        .allocate(&backend, &request(MIB, device_local))
+        .unwrap();
+
+    assert_eq!(a.memory(), b.memory());
+    assert_ne!(a.offset(), b.offset());
+    assert!(a.mapped_ptr().is_none());
+    assert_eq!(allocator.block_count(), 1);
+    assert_eq!(backend.live_count(), 1);
+
+    allocator.free(&backend, &a);
+    allocator.free(&backend, &b);
+    // the last block is kept around
+    assert_eq!(backend.live_count(), 1);
+
+    allocator.destroy(&backend);
+    assert_eq!(backend.live_count(), 0);
+}
+
+#[test]
+fn creates_and_frees_extra_blocks() {
+    let backend = MockBackend::default();
+    let allocator = MemoryAllocator::new(properties(), 1).with_block_size(16 * MIB);
+
+    let device_local = vk::MemoryPropertyFlags::DEVICE_LOCAL;
+    let allocations: Vec<_> = (0..3)
+        .map(|_| {
+            allocator
+                .allocate(&backend, &request(6 * MIB, device_local))
+                .unwrap()
+        })
+        .collect();
+
+    assert_eq!(allocator.block_count(), 2);
+
+    for allocation in &allocations {
+        allocator.free(&backend, allocation);
+    }
+    assert_eq!(allocator.block_count(), 1);
+
+    allocator.destroy(&backend);
+    assert_eq!(backend.live_count(), 0);
+}
+
+#[test]
+fn large_and_dedicated_allocations() {
+    let backend = MockBackend::default();
+    let allocator = MemoryAllocator::new(properties(), 1).with_block_size(16 * MIB);
+
+    let device_local = vk::MemoryPropertyFlags::DEVICE_LOCAL;
+    let large = allocator
+        .allocate(&backend, &request(12 * MIB, device_local))
+        .unwrap();
+    assert!(large.is_dedicated());
+
+    let dedicated = allocator
+        .allocate(
+            &backend,
+            &AllocationRequest {
+                dedicated: true,
+                ..request(MIB, device_local)
+            },
+        )
+        .unwrap();
+    assert!(dedicated.is_dedicated());
+    assert_eq!(allocator.block_count(), 0);
+    assert_eq!(backend.live_count(), 2);
+
+    allocator.free(&backend, &large);
+    allocator.free(&backend, &dedicated);
+    assert_eq!(backend.live_count(), 0);
+}
+
+#[test]
+fn host_visible_memory_is_mapped() {
+    let backend = MockBackend::default();
+    let allocator = MemoryAllocator::new(properties(), 1);
+
+    // the host visible heap is 64MB so its blocks are capped at 8MB
+    assert_eq!(allocator.block_size(1), 8 * MIB);
+
+    let host = vk::MemoryPropertyFlags::HOST_VISIBLE;
+    let a = allocator.allocate(&backend, &request(1024, host)).unwrap();
+    let b = allocator.allocate(&backend, &request(1024, host)).unwrap();
+
+    assert_eq!(a.memory_type_index(), 1);
+    let (a_ptr, b_ptr) = (a.mapped_ptr().unwrap(), b.mapped_ptr().unwrap());
+    assert_eq!(
+        b_ptr.as_ptr() as u64 - a_ptr.as_ptr() as u64,
+        b.offset() - a.offset()
+    );
+
+    allocator.free(&backend, &a);
+    allocator.free(&backend, &b);
+    allocator.destroy(&backend);
+}
+
+#[test]
+fn reports_usage_per_heap_and_category() {
+    let backend = MockBackend::default();
+    let allocator = MemoryAllocator::new(properties(), 1).with_block_size(16 * MIB);
+
+    let device_local = vk::MemoryPropertyFlags::DEVICE_LOCAL;
+    let buffer = allocator
+        .allocate(&backend, &request(MIB, device_local))
+        .unwrap();
+    let transient = allocator
+        .allocate(
+            &backend,
+            &AllocationRequest {
+                category: MemoryCategory::Named("transient"),
+                ..request(2 * MIB, device_local)
+            },
+        )
+        .unwrap();
+    let large = allocator
+        .allocate(&backend, &request(12 * MIB, device_local))
+        .unwrap();
+
+    let report = allocator.report();
+    let heap = &report.heaps[0];
+    assert_eq!(heap.allocated, 16 * MIB + 12 * MIB);
+    assert_eq!(heap.used, 15 * MIB);
+    assert_eq!(heap.block_count, 1);
+    assert_eq!(heap.dedicated_count, 1);
+    assert_eq!(heap.allocation_count, 3);
+    assert_eq!(heap.budget, None);
+    assert_eq!(report.heaps[1].allocated, 0);
+
+    assert_eq!(
+        report.category(MemoryCategory::Buffer),
+        CategoryUsage {
+            bytes: 13 * MIB,
+            allocation_count: 2
+        }
+    );
+    assert_eq!(
+        report.category(MemoryCategory::Named("transient")).bytes,
+        2 * MIB
+    );
+
+    // freeing the first allocation leaves a hole in front of the second one
+    allocator.free(&backend, &buffer);
+    let report = allocator.report();
+    assert!(report.heaps[0].fragmentation > 0.0);
+    assert_eq!(report.category(MemoryCategory::Buffer).allocation_count, 1);
+
+    allocator.free(&backend, &transient);
+    allocator.free(&backend, &large);
+    let report = allocator.report();
+    assert_eq!(report.used(), 0);
+    assert_eq!(report.heaps[0].fragmentation, 0.0);
+    assert_eq!(
+        report.category(MemoryCategory::Named("transient")),
+        CategoryUsage::default()
+    );
+
+    allocator.destroy(&backend);
+}
+
+#[test]
+fn non_coherent_ranges_cover_whole_atoms() {
+    assert_eq!(non_coherent_range(0, 100, 64), (0, 128));
+    assert_eq!(non_coherent_range(70, 10, 64), (64, 64));
+    assert_eq!(non_coherent_range(60, 10, 64), (0, 128));
+    assert_eq!(non_coherent_range(128, 64, 64), (128, 64));
+}
+
+#[test]
+fn pads_non_coherent_allocations() {
+    let mut properties = properties();
+    properties.memory_types[1].property_flags = vk::MemoryPropertyFlags::HOST_VISIBLE;
+
+    let backend = MockBackend::default();
+    let allocator = MemoryAllocator::new(properties, 1).with_non_coherent_atom_size(64);
+
+    let host = vk::MemoryPropertyFlags::HOST_VISIBLE;
+    let mut request = request(100, host);
+    request.requirements.alignment = 4;
+
+    let a = allocator.allocate(&backend, &request).unwrap();
+    let b = allocator.allocate(&backend, &request).unwrap();
+
+    assert!(!a.is_coherent());
+    assert_eq!(a.size(), 128);
+    assert_eq!(b.offset() % 64, 0);
+    assert!(b.offset() >= a.offset() + 128);
+
+    allocator.free(&backend, &a);
+    allocator.free(&backend, &b);
+    allocator.destroy(&backend);
+}
@@ -0,0 +1,65 @@
+use ash::vk;
+use rendering::prelude::*;
+
+#[test]
+fn converts_bgra_to_rgba() {
+    let image =
+        RgbaImage::from_raw(vk::Format::B8G8R8A8_UNORM, 2, 1, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+    assert_eq!(image.pixels, [3, 2, 1, 4, 7, 6, 5, 8]);
+    assert_eq!(image.pixel(1, 0), [7, 6, 5, 8]);
+}
+
+#[test]
+fn fills_missing_channels() {
+    let image = RgbaImage::from_raw(vk::Format::R8_UNORM, 1, 2, &[10, 20]).unwrap();
+    assert_eq!(image.pixel(0, 1), [20, 0, 0, 255]);
+
+    let image = RgbaImage::from_raw(vk::Format::R8G8_UNORM, 1, 1, &[10, 20]).unwrap();
+    assert_eq!(image.pixel(0, 0), [10, 20, 0, 255]);
+}
+
+#[test]
+fn converts_float_formats() {
+    let texel: Vec<u8> = [0.0f32, 0.5, 1.0, 2.0]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let image = RgbaImage::from_raw(vk::Format::R32G32B32A32_SFLOAT, 1, 1, &texel).unwrap();
+    assert_eq!(image.pixel(0, 0), [0, 128, 255, 255]);
+
+    // 0.0, 0.5, 1.0 and -1.0 as half floats
+    let texel: Vec<u8> = [0x0000u16, 0x3800, 0x3c00, 0xbc00]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let image = RgbaImage::from_raw(vk::Format::R16G16B16A16_SFLOAT, 1, 1, &texel).unwrap();
+    assert_eq!(image.pixel(0, 0), [0, 128, 255, 0]);
+}
+
+#[test]
+fn rejects_unsupported_formats_and_wrong_sizes() {
+    assert!(RgbaImage::from_raw(vk::Format::D32_SFLOAT, 1, 1, &[0; 4]).is_err());
+    assert!(RgbaImage::from_raw(vk::Format::R8G8B8A8_UNORM, 2, 2, &[0; 4]).is_err());
+    assert_eq!(format_texel_size(vk::Format::R16G16B16A16_SFLOAT), Some(8));
+}
+
+#[test]
+fn writes_png() {
+    let image = RgbaImage {
+        width: 2,
+        height: 1,
+        pixels: vec![255, 0, 0, 255, 0, 255, 0, 128],
+    };
+
+    let mut bytes = vec![];
+    image.write_png(&mut bytes).unwrap();
+
+    let mut reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+
+    assert_eq!((info.width, info.height), (2, 1));
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert_eq!(&pixels[..info.buffer_size()], image.pixels.as_slice());
+}
@@ -0,0 +1,38 @@
+use ash::vk::{self, Handle};
+use rendering::prelude::{RenderingAttachment, RenderingAttachments};
+
+#[test]
+fn builds_attachment_infos() {
+    let view = vk::ImageView::from_raw(1);
+
+    let color = RenderingAttachment::color(view).clear_color([0.1, 0.2, 0.3, 1.0]);
+    let info = color.info();
+    assert_eq!(info.image_view, view);
+    assert_eq!(info.image_layout, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+    assert_eq!(info.load_op, vk::AttachmentLoadOp::CLEAR);
+    assert_eq!(info.store_op, vk::AttachmentStoreOp::STORE);
+    assert_eq!(
+        unsafe { info.clear_value.color.float32 },
+        [0.1, 0.2, 0.3, 1.0]
+    );
+
+    let depth = RenderingAttachment::depth(view).info();
+    assert_eq!(
+        depth.image_layout,
+        vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+    );
+    assert_eq!(unsafe { depth.clear_value.depth_stencil.depth }, 1.0);
+}
+
+#[test]
+fn validates_attachments() {
+    assert!(RenderingAttachments::default().validate().is_err());
+
+    let null =
+        RenderingAttachments::default().color(RenderingAttachment::color(vk::ImageView::null()));
+    assert!(null.validate().is_err());
+
+    let depth_only = RenderingAttachments::default()
+        .depth(RenderingAttachment::depth(vk::ImageView::from_raw(1)));
+    assert!(depth_only.validate().is_ok());
+}
@@ -0,0 +1,92 @@
+use rendering::prelude::*;
+
+// OpEntryPoint GLCompute %1 "main" with a header in front of it
+fn compute_module_words() -> Vec<u32> {
+    vec![
+        SPIRV_MAGIC,
+        0x0001_0000,
+        0,
+        2,
+        0,
+        // OpEntryPoint, the nul terminator of "main" needs its own word
+        (5 << 16) | 15,
+        5,
+        1,
+        u32::from_le_bytes(*b"main"),
+        0,
+    ]
+}
+
+fn to_bytes(words: &[u32], big_endian: bool) -> Vec<u8> {
+    words
+        .iter()
+        .flat_map(|word| {
+            if big_endian {
+                word.to_be_bytes()
+            } else {
+                word.to_le_bytes()
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn parses_little_and_big_endian() {
+    let words = compute_module_words();
+
+    assert_eq!(spirv_words(&to_bytes(&words, false)).unwrap(), words);
+    assert_eq!(spirv_words(&to_bytes(&words, true)).unwrap(), words);
+}
+
+#[test]
+fn accepts_unaligned_input() {
+    let words = compute_module_words();
+
+    let mut bytes = vec![0u8];
+    bytes.extend(to_bytes(&words, false));
+
+    assert_eq!(spirv_words(&bytes[1..]).unwrap(), words);
+}
+
+#[test]
+fn rejects_invalid_binaries() {
+    let mut bytes = to_bytes(&compute_module_words(), false);
+
+    assert!(spirv_words(&bytes[..bytes.len() - 1]).is_err());
+    assert!(spirv_words(&bytes[..8]).is_err());
+    assert!(spirv_words(&[]).is_err());
+
+    bytes[0] = 0xff;
+    assert!(spirv_words(&bytes).is_err());
+}
+
+#[test]
+fn finds_entry_points() {
+    let entry_points = entry_points(&compute_module_words()).unwrap();
+
+    assert_eq!(
+        entry_points,
+        [EntryPoint {
+            name: "main".into(),
+            stage: ShaderStageFlags::COMPUTE,
+        }]
+    );
+}
+
+#[test]
+fn finds_mesh_shader_entry_points() {
+    let mut words = compute_module_words();
+    // MeshEXT
+    words[6] = 5365;
+
+    let entry_points = entry_points(&words).unwrap();
+    assert_eq!(entry_points[0].stage, ShaderStageFlags::MESH_EXT);
+}
+
+#[test]
+fn rejects_truncated_instructions() {
+    let mut words = compute_module_words();
+    words.pop();
+
+    assert!(entry_points(&words).is_err());
+}
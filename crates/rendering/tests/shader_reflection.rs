@@ -0,0 +1,251 @@
+use rendering::prelude::*;
+
+// the fixtures in `shaders/` correspond to these GLSL shaders
+//
+// double.comp:
+//   layout(local_size_x = 64) in;
+//   layout(set = 0, binding = 0) buffer Data { uint values[]; };
+//   layout(set = 0, binding = 1) uniform Params { uint offset; } params;
+//   layout(push_constant) uniform Push { uint count; float scale; } pc;
+//   values[gl_GlobalInvocationID.x] *= 2 for every id below pc.count
+//
+// textured.vert:
+//   layout(location = 0) in vec3 position;
+//   layout(location = 1) in vec2 uv;
+//   layout(set = 0, binding = 0) uniform Camera { mat4 view_proj; } camera;
+//   layout(push_constant) uniform Push { mat4 model; } pc;
+//
+// textured.frag:
+//   layout(set = 0, binding = 0) uniform Camera { mat4 view_proj; } camera;
+//   layout(set = 0, binding = 1) uniform sampler2D albedo;
+//   layout(set = 1, binding = 0) uniform texture2D textures[4];
+//   layout(set = 1, binding = 1) uniform sampler samplers;
+//   layout(push_constant) uniform Push { layout(offset = 64) vec4 tint; } pc;
+//
+// conflicting.frag is textured.frag with `camera` declared as a storage buffer
+
+fn reflect(bytes: &[u8]) -> ShaderReflection {
+    ShaderReflection::new(&spirv_words(bytes).unwrap(), "main").unwrap()
+}
+
+fn range(range: &PushConstantRange) -> (ShaderStageFlags, u32, u32) {
+    (range.stage_flags, range.offset, range.size)
+}
+
+fn binding(
+    binding: u32,
+    ty: VkDescriptorType,
+    count: u32,
+    stage_flags: ShaderStageFlags,
+) -> DescriptorType {
+    DescriptorType {
+        ty,
+        stage_flags,
+        count,
+        binding,
+    }
+}
+
+#[test]
+fn reflects_compute_shader() {
+    let reflection = reflect(include_bytes!("shaders/double.comp.spv"));
+    let stage = ShaderStageFlags::COMPUTE;
+
+    assert_eq!(reflection.stage, stage);
+    assert_eq!(
+        reflection.descriptor_sets[&0],
+        [
+            binding(0, VkDescriptorType::STORAGE_BUFFER, 1, stage),
+            binding(1, VkDescriptorType::UNIFORM_BUFFER, 1, stage),
+        ]
+    );
+    assert_eq!(
+        reflection.push_constants.as_ref().map(range),
+        Some((stage, 0, 8))
+    );
+    assert!(reflection.vertex_inputs.is_empty());
+}
+
+#[test]
+fn reflects_vertex_inputs() {
+    let reflection = reflect(include_bytes!("shaders/textured.vert.spv"));
+
+    assert_eq!(reflection.stage, ShaderStageFlags::VERTEX);
+    assert_eq!(
+        reflection.vertex_inputs,
+        [
+            VertexInput {
+                location: 0,
+                format: vk::Format::R32G32B32_SFLOAT,
+            },
+            VertexInput {
+                location: 1,
+                format: vk::Format::R32G32_SFLOAT,
+            },
+        ]
+    );
+    assert_eq!(reflection.push_constants.unwrap().size, 64);
+}
+
+#[test]
+fn reflects_images_and_arrays() {
+    let reflection = reflect(include_bytes!("shaders/textured.frag.spv"));
+    let stage = ShaderStageFlags::FRAGMENT;
+
+    assert_eq!(
+        reflection.descriptor_sets[&0],
+        [
+            binding(0, VkDescriptorType::UNIFORM_BUFFER, 1, stage),
+            binding(1, VkDescriptorType::COMBINED_IMAGE_SAMPLER, 1, stage),
+        ]
+    );
+    assert_eq!(
+        reflection.descriptor_sets[&1],
+        [
+            binding(0, VkDescriptorType::SAMPLED_IMAGE, 4, stage),
+            binding(1, VkDescriptorType::SAMPLER, 1, stage),
+        ]
+    );
+    assert_eq!(
+        reflection.push_constants.as_ref().map(range),
+        Some((stage, 64, 16))
+    );
+}
+
+#[test]
+fn merges_pipeline_stages() {
+    let merged = PipelineReflection::merge(&[
+        reflect(include_bytes!("shaders/textured.vert.spv")),
+        reflect(include_bytes!("shaders/textured.frag.spv")),
+    ])
+    .unwrap();
+
+    let both = ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT;
+
+    assert_eq!(
+        merged.descriptor_sets[&0],
+        [
+            binding(0, VkDescriptorType::UNIFORM_BUFFER, 1, both),
+            binding(
+                1,
+                VkDescriptorType::COMBINED_IMAGE_SAMPLER,
+                1,
+                ShaderStageFlags::FRAGMENT
+            ),
+        ]
+    );
+    assert_eq!(
+        merged
+            .push_constant_ranges
+            .iter()
+            .map(range)
+            .collect::<Vec<_>>(),
+        [(both, 0, 80)]
+    );
+    assert_eq!(merged.vertex_inputs.len(), 2);
+}
+
+#[test]
+fn reports_conflicting_bindings() {
+    let result = PipelineReflection::merge(&[
+        reflect(include_bytes!("shaders/textured.vert.spv")),
+        reflect(include_bytes!("shaders/conflicting.frag.spv")),
+    ]);
+
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("set 0 binding 0"), "{error}");
+}
+
+#[test]
+fn rejects_duplicate_stages() {
+    let vertex = reflect(include_bytes!("shaders/textured.vert.spv"));
+
+    assert!(PipelineReflection::merge(&[vertex.clone(), vertex]).is_err());
+}
+
+#[test]
+fn reports_missing_entry_point() {
+    let words = spirv_words(include_bytes!("shaders/double.comp.spv")).unwrap();
+
+    assert!(ShaderReflection::new(&words, "not_main").is_err());
+}
+
+#[test]
+fn rejects_truncated_instructions() {
+    // header followed by an OpTypeStruct without a result id
+    let words = [0x0723_0203, 0x0001_0000, 0, 1, 0, (1 << 16) | 30];
+
+    assert!(ShaderReflection::new(&words, "main").is_err());
+}
+
+// a vertex shader with one float vector input of `count` components at location 0
+fn vector_input_words(count: u32) -> Vec<u32> {
+    vec![
+        0x0723_0203,
+        0x0001_0000,
+        0,
+        6,
+        0,
+        // OpEntryPoint Vertex %1 "main" %5
+        (6 << 16) | 15,
+        0,
+        1,
+        u32::from_le_bytes(*b"main"),
+        0,
+        5,
+        // OpDecorate %5 Location 0
+        (4 << 16) | 71,
+        5,
+        30,
+        0,
+        // %2 = OpTypeFloat 32, %3 = OpTypeVector %2 count
+        (3 << 16) | 22,
+        2,
+        32,
+        (4 << 16) | 23,
+        3,
+        2,
+        count,
+        // %4 = OpTypePointer Input %3, %5 = OpVariable %4 Input
+        (4 << 16) | 32,
+        4,
+        1,
+        3,
+        (4 << 16) | 59,
+        4,
+        5,
+        1,
+    ]
+}
+
+#[test]
+fn rejects_invalid_vector_sizes() {
+    let reflection = ShaderReflection::new(&vector_input_words(3), "main").unwrap();
+    assert_eq!(
+        reflection.vertex_inputs[0].format,
+        vk::Format::R32G32B32_SFLOAT
+    );
+
+    assert!(ShaderReflection::new(&vector_input_words(0), "main").is_err());
+    assert!(ShaderReflection::new(&vector_input_words(5), "main").is_err());
+}
+
+#[test]
+fn sizes_runtime_arrays() {
+    let mut reflection = PipelineReflection::default();
+    reflection.descriptor_sets.insert(
+        0,
+        vec![
+            binding(0, VkDescriptorType::SAMPLED_IMAGE, 0, ShaderStageFlags::FRAGMENT),
+            binding(1, VkDescriptorType::SAMPLER, 1, ShaderStageFlags::FRAGMENT),
+        ],
+    );
+
+    assert!(reflection.set_bindings(None).is_err());
+
+    let sets = reflection.set_bindings(Some(1024)).unwrap();
+    let (bindings, flags) = &sets[0];
+    assert_eq!(bindings[0].count, 1024);
+    assert_eq!(flags[0], vk::DescriptorBindingFlags::PARTIALLY_BOUND);
+    assert!(flags[1].is_empty());
+}
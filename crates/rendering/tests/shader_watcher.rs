@@ -0,0 +1,93 @@
+use std::{
+    fs::File,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use rendering::prelude::*;
+
+const SHADER: &[u8] = include_bytes!("shaders/double.comp.spv");
+
+fn temp_shader(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{name}.spv", std::process::id()));
+    std::fs::write(&path, SHADER).unwrap();
+    path
+}
+
+// rewrites the file with a later timestamp, the filesystem clock can be too coarse otherwise
+fn touch(path: &PathBuf, age: u64) {
+    std::fs::write(path, SHADER).unwrap();
+    let time = SystemTime::now() + Duration::from_secs(age);
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(time)
+        .unwrap();
+}
+
+#[test]
+fn rebuilds_changed_shaders() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+    let watcher = ShaderWatcher::new(device.clone());
+
+    let path = temp_shader("rebuild");
+    let builds = Arc::new(AtomicUsize::new(0));
+    let counter = builds.clone();
+    let pipeline = watcher
+        .pipeline(&[&path], move |_| {
+            Ok(Arc::new(counter.fetch_add(1, Ordering::Relaxed)))
+        })
+        .unwrap();
+
+    let report = watcher.reload_changed(&[]);
+    assert!(report.reloaded_shaders.is_empty());
+    assert_eq!(*pipeline.get(), 0);
+
+    touch(&path, 10);
+    let report = watcher.reload_changed(&[]);
+    assert_eq!(report.reloaded_shaders, vec![path.clone()]);
+    assert_eq!(report.rebuilt_pipelines, 1);
+    assert!(report.errors.is_empty());
+    assert_eq!(*pipeline.get(), 1);
+
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn failed_rebuild_keeps_the_current_pipeline() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+    let watcher = ShaderWatcher::new(device.clone());
+
+    let path = temp_shader("failed");
+    let builds = Arc::new(AtomicUsize::new(0));
+    let counter = builds.clone();
+    let pipeline = watcher
+        .pipeline(&[&path], move |_| {
+            match counter.fetch_add(1, Ordering::Relaxed) {
+                1 => anyhow::bail!("broken pipeline"),
+                n => Ok(Arc::new(n)),
+            }
+        })
+        .unwrap();
+
+    touch(&path, 10);
+    let report = watcher.reload_changed(&[]);
+    assert_eq!(report.rebuilt_pipelines, 0);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(*pipeline.get(), 0);
+
+    // nothing stale is left behind, the next change swaps in the new build
+    touch(&path, 20);
+    let report = watcher.reload_changed(&[]);
+    assert_eq!(report.rebuilt_pipelines, 1);
+    assert_eq!(*pipeline.get(), 2);
+
+    std::fs::remove_file(path).ok();
+}
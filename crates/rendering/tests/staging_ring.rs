@@ -0,0 +1,55 @@
+use rendering::prelude::*;
+
+#[test]
+fn allocates_in_order_and_wraps() {
+    let mut ring = StagingRing::new(1024);
+
+    assert_eq!(ring.allocate(400, 16, 1), Some(0));
+    assert_eq!(ring.allocate(400, 16, 2), Some(400));
+    // only 224 bytes left at the end and the front is still in use
+    assert_eq!(ring.allocate(300, 16, 3), None);
+
+    ring.release(1);
+    assert_eq!(ring.used(), 400);
+
+    // doesn't fit at the end, so it starts over at the front
+    assert_eq!(ring.allocate(300, 16, 3), Some(0));
+    assert_eq!(ring.used(), 400 + 224 + 300);
+
+    ring.release(3);
+    assert_eq!(ring.used(), 0);
+}
+
+#[test]
+fn respects_alignment() {
+    let mut ring = StagingRing::new(1024);
+
+    assert_eq!(ring.allocate(10, 16, 1), Some(0));
+    assert_eq!(ring.allocate(10, 16, 1), Some(16));
+    assert_eq!(ring.allocate(10, 256, 2), Some(256));
+
+    ring.release(1);
+    assert_eq!(ring.used(), 266 - 26);
+}
+
+#[test]
+fn rejects_too_large_and_empty_allocations() {
+    let mut ring = StagingRing::new(1024);
+
+    assert_eq!(ring.allocate(2048, 1, 1), None);
+    assert_eq!(ring.allocate(0, 1, 1), None);
+    assert_eq!(ring.allocate(1024, 1, 1), Some(0));
+    assert_eq!(ring.allocate(1, 1, 2), None);
+}
+
+#[test]
+fn cancel_gives_back_the_newest_submission() {
+    let mut ring = StagingRing::new(1024);
+
+    assert_eq!(ring.allocate(512, 1, 1), Some(0));
+    assert_eq!(ring.allocate(256, 1, 2), Some(512));
+
+    ring.cancel(2);
+    assert_eq!(ring.used(), 512);
+    assert_eq!(ring.allocate(512, 1, 2), Some(512));
+}
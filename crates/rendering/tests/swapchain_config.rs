@@ -0,0 +1,67 @@
+use rendering::prelude::{vk, SurfaceSupport, SwapchainConfig, VsyncMode};
+
+fn support(formats: &[vk::Format], present_modes: &[vk::PresentModeKHR]) -> SurfaceSupport {
+    SurfaceSupport {
+        capabilities: vk::SurfaceCapabilitiesKHR {
+            min_image_count: 2,
+            max_image_count: 3,
+            supported_composite_alpha: vk::CompositeAlphaFlagsKHR::INHERIT,
+            supported_usage_flags: vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::TRANSFER_DST,
+            ..Default::default()
+        },
+        formats: formats
+            .iter()
+            .map(|v| vk::SurfaceFormatKHR {
+                format: *v,
+                color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            })
+            .collect(),
+        present_modes: present_modes.to_vec(),
+    }
+}
+
+#[test]
+fn picks_supported_settings() {
+    let support = support(
+        &[vk::Format::B8G8R8A8_UNORM, vk::Format::R8G8B8A8_SRGB],
+        &[vk::PresentModeKHR::FIFO, vk::PresentModeKHR::IMMEDIATE],
+    );
+
+    let infos = SwapchainConfig::default()
+        .vsync(VsyncMode::Mailbox)
+        .image_count(8)
+        .usage(vk::ImageUsageFlags::TRANSFER_DST)
+        .resolve(&support)
+        .unwrap();
+
+    // the srgb format wins over the unorm one that is listed first
+    assert_eq!(infos.format.format, vk::Format::R8G8B8A8_SRGB);
+    // mailbox isn't there, immediate would tear, so it's fifo
+    assert_eq!(infos.present_mode, vk::PresentModeKHR::FIFO);
+    assert_eq!(infos.image_count, 3);
+    assert_eq!(infos.composite_alpha, vk::CompositeAlphaFlagsKHR::INHERIT);
+    assert!(infos.usage.contains(vk::ImageUsageFlags::COLOR_ATTACHMENT));
+
+    // unsupported usage is an error instead of a silent fallback
+    let storage = SwapchainConfig::default().usage(vk::ImageUsageFlags::STORAGE);
+    assert!(storage.resolve(&support).is_err());
+}
+
+#[test]
+fn falls_back_to_what_the_surface_has() {
+    let support = support(&[vk::Format::B8G8R8A8_UNORM], &[vk::PresentModeKHR::FIFO]);
+
+    let config = SwapchainConfig::default()
+        .formats(SwapchainConfig::hdr_formats())
+        .vsync(VsyncMode::FifoRelaxed);
+
+    assert_eq!(
+        config.choose_format(&support).unwrap().format,
+        vk::Format::B8G8R8A8_UNORM
+    );
+    assert_eq!(
+        config.choose_present_mode(&support),
+        vk::PresentModeKHR::FIFO
+    );
+}
@@ -0,0 +1,35 @@
+use rendering::prelude::{choose_extent, vk};
+
+fn capabilities(current: vk::Extent2D) -> vk::SurfaceCapabilitiesKHR {
+    vk::SurfaceCapabilitiesKHR {
+        current_extent: current,
+        min_image_extent: vk::Extent2D {
+            width: 1,
+            height: 1,
+        },
+        max_image_extent: vk::Extent2D {
+            width: 1024,
+            height: 1024,
+        },
+        ..Default::default()
+    }
+}
+
+fn extent(width: u32, height: u32) -> vk::Extent2D {
+    vk::Extent2D { width, height }
+}
+
+#[test]
+fn extent_follows_surface() {
+    // the surface decides, the window size is ignored
+    let chosen = choose_extent(&capabilities(extent(800, 600)), extent(1920, 1080));
+    assert_eq!(chosen, extent(800, 600));
+
+    // minimized windows report a zero extent
+    let chosen = choose_extent(&capabilities(extent(0, 0)), extent(800, 600));
+    assert_eq!(chosen, extent(0, 0));
+
+    // the swapchain decides, the window size gets clamped
+    let chosen = choose_extent(&capabilities(extent(u32::MAX, u32::MAX)), extent(1920, 600));
+    assert_eq!(chosen, extent(1024, 600));
+}
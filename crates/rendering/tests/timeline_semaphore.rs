@@ -0,0 +1,87 @@
+use std::{sync::Arc, time::Duration};
+
+use rendering::prelude::*;
+
+#[test]
+fn host_signal_and_wait() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+    if !device.supports_timeline_semaphores() {
+        return;
+    }
+
+    let semaphore = Semaphore::timeline(device.clone(), 0).unwrap();
+    assert!(!semaphore.wait(1, Some(Duration::ZERO)).unwrap());
+
+    semaphore.signal(1).unwrap();
+    assert!(semaphore.wait(1, None).unwrap());
+    assert_eq!(semaphore.value().unwrap(), 1);
+
+    let binary = Semaphore::binary(device.clone()).unwrap();
+    assert!(binary.signal(1).is_err());
+}
+
+#[test]
+fn resources_are_released_at_their_value() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+    if !device.supports_timeline_semaphores() {
+        return;
+    }
+
+    let semaphore = Semaphore::timeline(device.clone(), 0).unwrap();
+    let resource = Arc::new(());
+    semaphore.release_at(2, resource.clone()).unwrap();
+
+    semaphore.signal(1).unwrap();
+    assert_eq!(Arc::strong_count(&resource), 2);
+
+    semaphore.signal(2).unwrap();
+    assert_eq!(Arc::strong_count(&resource), 1);
+}
+
+#[test]
+fn submissions_release_at_their_timeline_signal() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+    if !device.supports_timeline_semaphores() {
+        return;
+    }
+
+    let semaphore = Semaphore::timeline(device.clone(), 0).unwrap();
+    let resource = Arc::new(());
+    Submission::new(vec![])
+        .signal_value(&semaphore, 1)
+        .keep_alive(resource.clone())
+        .submit(device.queue(), None)
+        .unwrap();
+
+    assert!(semaphore.wait(1, None).unwrap());
+    assert_eq!(Arc::strong_count(&resource), 1);
+}
+
+#[test]
+fn dropping_waits_for_timeline_submissions() {
+    let instance = Instance::new().unwrap();
+    let device = Device::new(instance.clone()).unwrap();
+    if !device.supports_timeline_semaphores() {
+        return;
+    }
+
+    let pool = CommandPool::new(device.clone()).unwrap();
+    let command_buffer = CommandBuffer::new(pool, device.clone()).unwrap();
+    command_buffer.begin();
+    command_buffer.end();
+
+    let semaphore = Semaphore::timeline(device.clone(), 0).unwrap();
+    let resource = Arc::new(());
+    Submission::new(vec![command_buffer])
+        .signal_value(&semaphore, 1)
+        .keep_alive(resource.clone())
+        .submit(device.queue(), None)
+        .unwrap();
+
+    // the command buffer and the resource only go once the submission finished
+    drop(semaphore);
+    assert_eq!(Arc::strong_count(&resource), 1);
+}
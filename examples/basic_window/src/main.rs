@@ -2,7 +2,9 @@ use std::sync::Arc;
 
 use neutron::*;
 use rendering::prelude::{
-    vk, BufferCreateInfo, BufferUsageFlags, CommandBuffer, CommandPool, DescriptorPool, DescriptorSetLayout, DescriptorSets, DescriptorType, Device, Fence, Instance, MemoryPropertyFlags, RenderPass, RenderingInfo, ShaderStageFlags, Subbuffer, Surface, Swapchain, VKDebugger
+    DescriptorPool, DescriptorSetLayout, DescriptorSets, DescriptorType, Device, FrameContext,
+    Instance, RenderPass, RenderingAttachment, RenderingAttachments, ShaderStageFlags, Surface,
+    Swapchain, VKDebugger,
 };
 use winit::{event_loop::EventLoop, window::Window};
 
@@ -21,15 +23,18 @@ fn main() {
 
     let surface = Surface::new(instance.clone(), window.clone()).unwrap();
 
-    let device = Device::new(instance.clone()).unwrap();
-
-    let _fence = Fence::new(device.clone());
+    let device = Device::builder(instance.clone())
+        .surface(surface.clone())
+        .build()
+        .unwrap();
 
     let swapchain = Swapchain::new(device.clone(), surface.clone()).unwrap();
-    let command_pool = CommandPool::new(device.clone()).unwrap();
-    let command_buffer = CommandBuffer::new(command_pool.clone(), device.clone()).unwrap();
+    // every swapchain image gets a framebuffer for it, also after recreation
+    let render_pass = RenderPass::new(device.clone(), swapchain.format()).unwrap();
+    swapchain.set_render_pass(render_pass).unwrap();
 
-    let _render_pass = RenderPass::new(device.clone(), swapchain.format()).unwrap();
+    // the frame context owns the swapchain, it gets replaced when the window is resized
+    let mut frames = FrameContext::new(device.clone(), swapchain, 2).unwrap();
 
 
     let descriptors = &[DescriptorType {
@@ -43,24 +48,47 @@ fn main() {
     let descriptor_layout = DescriptorSetLayout::new(device.clone(), descriptors).unwrap();
     let _descriptor_sets = DescriptorSets::new(descriptor_pool.clone(), &[descriptor_layout]);
 
-    command_buffer.begin();
-
     event_loop
-        .run(|event, target| match event {
-            winit::event::Event::WindowEvent {
+        .run(|event, target| {
+            if let winit::event::Event::WindowEvent {
                 window_id: _,
                 event,
-            } => match event {
-                winit::event::WindowEvent::CloseRequested => target.exit(),
-                winit::event::WindowEvent::RedrawRequested => {
-                    let (index, _) = swapchain.aquire_next_image();
-                    swapchain.present(index, device.queue());
-                    // window.request_redraw();
+            } = event
+            {
+                match event {
+                    winit::event::WindowEvent::CloseRequested => target.exit(),
+                    winit::event::WindowEvent::Resized(size) => {
+                        frames.resize();
+                        // redraws stop while the window has no size, this starts them again
+                        if size.width > 0 && size.height > 0 {
+                            window.request_redraw();
+                        }
+                    }
+                    winit::event::WindowEvent::RedrawRequested => {
+                        // skipped while the window is minimized
+                        if let Some(frame) = frames.begin_frame().unwrap() {
+                            if device.supports_dynamic_rendering() {
+                                let attachments = RenderingAttachments::default().color(
+                                    RenderingAttachment::from_view(&frame.view)
+                                        .clear_color([0.1, 0.1, 0.1, 1.0]),
+                                );
+                                // rendering ends when the scope is dropped
+                                let _scope = frame
+                                    .command_buffer
+                                    .rendering(frame.extent, &attachments)
+                                    .unwrap();
+                            }
+                            frames.end_frame(frame).unwrap();
+                        }
+
+                        let size = window.inner_size();
+                        if size.width > 0 && size.height > 0 {
+                            window.request_redraw();
+                        }
+                    }
+                    _ => {}
                 }
-                _ => {}
-            },
-
-            _ => {}
+            }
         })
         .unwrap();
 }